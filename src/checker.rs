@@ -0,0 +1,637 @@
+use crate::expr::{BinaryExpr, CallExpr, Expr, UnaryExpr};
+use crate::interpreter::TYPE_NAMES;
+use crate::stmt::{FunStmt, Stmt, VarStmt};
+use crate::token::{Token, TokenType};
+
+use std::collections::HashMap;
+
+// A static pass over annotated code, run before the interpreter by
+// `rena check` and `--typecheck`. Types are known for literals,
+// operators, names that carry an annotation and variables inferred from
+// their initializer; everything else is `Any`, which matches every
+// type. Problems are only reported where an annotation is involved, so
+// unannotated code stays dynamically typed.
+#[derive(Clone, Debug, PartialEq)]
+enum Type {
+    Any,
+    Named(String), // one of `TYPE_NAMES` or a declared enum or struct
+}
+
+impl Type {
+    fn named(name: &str) -> Type {
+        Type::Named(name.to_owned())
+    }
+
+    fn is(&self, name: &str) -> bool {
+        matches!(self, Type::Named(own) if own == name)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Signature {
+    params: Vec<Type>,
+    ret: Type,
+    typed: bool, // whether any parameter or the return is annotated
+}
+
+#[derive(Clone, Debug)]
+struct Binding {
+    ty: Type,
+    annotated: bool,
+    signature: Option<Signature>, // set for declared functions
+}
+
+pub struct Checker {
+    scopes: Vec<HashMap<String, Binding>>,
+    returns: Vec<Type>, // declared return types of enclosing functions
+    // enums and structs declared so far, with "enum" or "struct"
+    user_types: HashMap<String, &'static str>,
+    errors: Vec<String>,
+}
+
+impl Checker {
+    pub fn new() -> Checker {
+        Checker {
+            scopes: vec![HashMap::new()],
+            returns: vec![],
+            user_types: HashMap::new(),
+            errors: vec![],
+        }
+    }
+
+    // Returns every mismatch found, formatted with its line number
+    pub fn check(mut self, statements: &[Stmt]) -> Vec<String> {
+        for stmt in statements {
+            self.statement(stmt);
+        }
+        self.errors
+    }
+
+    fn accepts(&self, expected: &Type, found: &Type) -> bool {
+        match (expected, found) {
+            (Type::Any, _) | (_, Type::Any) => true,
+            // a declared enum is also an `enum`, a struct a `struct`
+            (Type::Named(l), Type::Named(r)) => {
+                l == r || self.user_types.get(r) == Some(&l.as_str())
+            }
+        }
+    }
+
+    fn report(&mut self, line: i32, message: String) {
+        self.errors.push(format!("[line {}] {}", line, message));
+    }
+
+    fn annotation(&mut self, name: &Option<Token>) -> Type {
+        match name {
+            None => Type::Any,
+            Some(token) if token.lexeme == "any" => Type::Any,
+            Some(token)
+                if TYPE_NAMES.contains(&token.lexeme.as_str())
+                    || self.user_types.contains_key(&token.lexeme) =>
+            {
+                Type::named(&token.lexeme)
+            }
+            Some(token) => {
+                self.report(
+                    token.line,
+                    format!("Unknown type '{}'.", token.lexeme),
+                );
+                Type::Any
+            }
+        }
+    }
+
+    fn define(&mut self, name: &str, binding: Binding) {
+        self.scopes
+            .last_mut()
+            .expect("Checker has no scope.")
+            .insert(name.to_owned(), binding);
+    }
+
+    fn lookup(&mut self, name: &str) -> Option<&mut Binding> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+    }
+
+    fn statement(&mut self, statement: &Stmt) {
+        match statement {
+            Stmt::Var(stmt) => self.var(stmt),
+            Stmt::Print(stmt) => {
+                self.expression(&stmt.expr);
+                for arg in &stmt.args {
+                    self.expression(arg);
+                }
+            }
+            Stmt::Expression(stmt) => {
+                self.expression(&stmt.expr);
+            }
+            Stmt::Block(stmt) => {
+                self.scopes.push(HashMap::new());
+                for stmt in stmt.statements.iter() {
+                    self.statement(stmt);
+                }
+                self.scopes.pop();
+            }
+            Stmt::If(stmt) => {
+                self.expression(&stmt.condition);
+                self.statement(&stmt.then_branch);
+                if let Some(branch) = &stmt.else_branch {
+                    self.statement(branch);
+                }
+            }
+            Stmt::While(stmt) => {
+                self.expression(&stmt.condition);
+                self.statement(&stmt.body);
+            }
+            Stmt::For(stmt) => {
+                self.scopes.push(HashMap::new());
+                if let Some(init) = &stmt.init {
+                    self.statement(init);
+                }
+                if let Some(condition) = &stmt.condition {
+                    self.expression(condition);
+                }
+                if let Some(increment) = &stmt.increment {
+                    self.expression(increment);
+                }
+                self.statement(&stmt.body);
+                self.scopes.pop();
+            }
+            Stmt::ForIn(stmt) => {
+                let iterable = self.expression(&stmt.iterable);
+                let item = if iterable.is("range") {
+                    Type::named("number")
+                } else if iterable.is("string") {
+                    Type::named("string")
+                } else {
+                    Type::Any
+                };
+                self.scopes.push(HashMap::new());
+                self.define(
+                    &stmt.name.lexeme,
+                    Binding {
+                        ty: item,
+                        annotated: false,
+                        signature: None,
+                    },
+                );
+                self.statement(&stmt.body);
+                self.scopes.pop();
+            }
+            Stmt::Function(stmt) => self.function(stmt),
+            Stmt::Return(stmt) => {
+                let value = match &stmt.value {
+                    Some(value) => self.expression(value),
+                    None => Type::named("nil"),
+                };
+                if let Some(expected) = self.returns.last().cloned() {
+                    if !self.accepts(&expected, &value) {
+                        self.mismatch(
+                            stmt.keyword.line,
+                            &expected,
+                            &value,
+                            "return value",
+                        );
+                    }
+                }
+            }
+            Stmt::Enum(stmt) => self.user_type(&stmt.name, "enum"),
+            Stmt::Struct(stmt) => self.user_type(&stmt.name, "struct"),
+            Stmt::Test(stmt) => {
+                self.scopes.push(HashMap::new());
+                for stmt in stmt.body.iter() {
+                    self.statement(stmt);
+                }
+                self.scopes.pop();
+            }
+            Stmt::Assert(stmt) => {
+                self.expression(&stmt.condition);
+                if let Some(message) = &stmt.message {
+                    self.expression(message);
+                }
+            }
+            Stmt::Yield(stmt) => {
+                if let Some(value) = &stmt.value {
+                    self.expression(value);
+                }
+            }
+        }
+    }
+
+    fn user_type(&mut self, name: &Token, kind: &'static str) {
+        self.user_types.insert(name.lexeme.clone(), kind);
+        self.define(
+            &name.lexeme,
+            Binding {
+                ty: Type::named(kind),
+                annotated: false,
+                signature: None,
+            },
+        );
+    }
+
+    fn mismatch(
+        &mut self,
+        line: i32,
+        expected: &Type,
+        found: &Type,
+        what: &str,
+    ) {
+        if let (Type::Named(expected), Type::Named(found)) =
+            (expected, found)
+        {
+            self.report(
+                line,
+                format!(
+                    "Expected {} for {} but got {}.",
+                    expected, what, found
+                ),
+            );
+        }
+    }
+
+    fn var(&mut self, stmt: &VarStmt) {
+        let declared = self.annotation(&stmt.type_name);
+        let name = &stmt.names[0];
+        let mut ty = declared.clone();
+        if let Some(init) = &stmt.init {
+            let value = self.expression(init);
+            if !self.accepts(&declared, &value) {
+                let what = format!("'{}'", name.lexeme);
+                self.mismatch(name.line, &declared, &value, &what);
+            }
+            // without an annotation the type is inferred from the value,
+            // unpacked variables are never annotated nor inferred
+            if stmt.type_name.is_none() && !stmt.unpack {
+                ty = value;
+            }
+        }
+        for name in &stmt.names {
+            self.define(
+                &name.lexeme,
+                Binding {
+                    ty: ty.clone(),
+                    annotated: stmt.type_name.is_some(),
+                    signature: None,
+                },
+            );
+        }
+    }
+
+    fn function(&mut self, stmt: &FunStmt) {
+        let params = stmt
+            .param_types
+            .iter()
+            .map(|ty| self.annotation(ty))
+            .collect::<Vec<_>>();
+        let declared = self.annotation(&stmt.return_type);
+        let ret = if stmt.is_generator {
+            if !self.accepts(&declared, &Type::named("generator")) {
+                self.report(
+                    stmt.name.line,
+                    format!(
+                        "Generator function '{}' can only return a generator.",
+                        stmt.name.lexeme
+                    ),
+                );
+            }
+            Type::named("generator")
+        } else {
+            declared.clone()
+        };
+        // defined before the body is checked so recursive calls resolve
+        self.define(
+            &stmt.name.lexeme,
+            Binding {
+                ty: Type::named("function"),
+                annotated: false,
+                signature: Some(Signature {
+                    params: params.clone(),
+                    ret,
+                    typed: stmt.return_type.is_some()
+                        || stmt.param_types.iter().any(Option::is_some),
+                }),
+            },
+        );
+
+        self.scopes.push(HashMap::new());
+        for ((param, ty), annotation) in
+            stmt.params.iter().zip(params).zip(&stmt.param_types)
+        {
+            self.define(
+                &param.lexeme,
+                Binding {
+                    ty,
+                    annotated: annotation.is_some(),
+                    signature: None,
+                },
+            );
+        }
+        // a generator's `return` only ends it, its value is discarded
+        self.returns.push(if stmt.is_generator {
+            Type::Any
+        } else {
+            declared
+        });
+        for stmt in stmt.body.iter() {
+            self.statement(stmt);
+        }
+        self.returns.pop();
+        self.scopes.pop();
+    }
+
+    // Infers the type of an expression, reporting mismatches inside it
+    fn expression(&mut self, expression: &Expr) -> Type {
+        match expression {
+            Expr::Nil => Type::named("nil"),
+            Expr::Number(_) => Type::named("number"),
+            Expr::Boolean(_) => Type::named("bool"),
+            Expr::String(_) => Type::named("string"),
+            Expr::Group(expr) => self.expression(&expr.expr),
+            Expr::Variable(expr) => match self.lookup(&expr.name.lexeme) {
+                Some(binding) => binding.ty.clone(),
+                None => Type::Any, // natives and globals from elsewhere
+            },
+            Expr::Assign(expr) => {
+                let value = self.expression(&expr.value);
+                let target = match self.lookup(&expr.name.lexeme) {
+                    Some(binding) if binding.annotated => {
+                        Some(binding.ty.clone())
+                    }
+                    Some(binding) => {
+                        // the name may now hold a different function
+                        binding.signature = None;
+                        binding.ty = Type::Any;
+                        None
+                    }
+                    None => None,
+                };
+                if let Some(target) = target {
+                    if !self.accepts(&target, &value) {
+                        let what = format!("'{}'", expr.name.lexeme);
+                        self.mismatch(
+                            expr.name.line,
+                            &target,
+                            &value,
+                            &what,
+                        );
+                    }
+                }
+                value
+            }
+            Expr::Unary(expr) => self.unary(expr),
+            Expr::Binary(expr) => self.binary(expr),
+            Expr::Call(expr) => self.call(expr),
+            Expr::OptionalCall(expr) => {
+                self.call(expr);
+                Type::Any // nil if the callee is
+            }
+            Expr::Coalesce(expr) => {
+                let left = self.expression(&expr.left);
+                let right = self.expression(&expr.right);
+                if left == right || left.is("nil") {
+                    right
+                } else {
+                    Type::Any
+                }
+            }
+            Expr::Get(expr) => {
+                self.expression(&expr.object);
+                Type::Any
+            }
+            Expr::Range(expr) => {
+                self.expression(&expr.start);
+                self.expression(&expr.end);
+                if let Some(step) = &expr.step {
+                    self.expression(step);
+                }
+                Type::named("range")
+            }
+            Expr::List(expr) => {
+                for element in &expr.elements {
+                    self.expression(element);
+                }
+                Type::named("list")
+            }
+            Expr::Tuple(expr) => {
+                for element in &expr.elements {
+                    self.expression(element);
+                }
+                Type::named("tuple")
+            }
+            Expr::Map(expr) => {
+                for (_, value) in &expr.entries {
+                    self.expression(value);
+                }
+                Type::named("map")
+            }
+            Expr::Index(expr) => {
+                self.expression(&expr.object);
+                self.expression(&expr.index);
+                Type::Any
+            }
+            Expr::SetIndex(expr) => {
+                self.expression(&expr.object);
+                self.expression(&expr.index);
+                self.expression(&expr.value)
+            }
+            Expr::Set(expr) => {
+                self.expression(&expr.object);
+                self.expression(&expr.value)
+            }
+            Expr::Is(expr) => {
+                self.expression(&expr.value);
+                Type::named("bool")
+            }
+        }
+    }
+
+    fn unary(&mut self, expr: &UnaryExpr) -> Type {
+        let right = self.expression(&expr.right);
+        match expr.oper.token_type {
+            TokenType::Minus => {
+                if let Type::Named(name) = &right {
+                    if name != "number" && self.declared(&expr.right) {
+                        self.report(
+                            expr.oper.line,
+                            format!(
+                                "Operator '-' can't be applied to {}.",
+                                name
+                            ),
+                        );
+                    }
+                }
+                Type::named("number")
+            }
+            _ => Type::named("bool"),
+        }
+    }
+
+    // Mirrors the operand rules of `Interpreter::binary`
+    fn binary(&mut self, expr: &BinaryExpr) -> Type {
+        let left = self.expression(&expr.left);
+        let right = self.expression(&expr.right);
+        let (l, r) = match (&left, &right) {
+            (Type::Named(l), Type::Named(r)) => (l.as_str(), r.as_str()),
+            _ => {
+                return match expr.oper.token_type {
+                    TokenType::Plus => {
+                        if left.is("number") || right.is("number") {
+                            Type::named("number")
+                        } else if left.is("string") || right.is("string") {
+                            Type::named("string")
+                        } else {
+                            Type::Any
+                        }
+                    }
+                    TokenType::Minus
+                    | TokenType::Star
+                    | TokenType::Slash => Type::named("number"),
+                    _ => Type::named("bool"),
+                }
+            }
+        };
+        let result = match expr.oper.token_type {
+            TokenType::EqualEqual | TokenType::BangEqual => Some("bool"),
+            TokenType::In => Some("bool"),
+            TokenType::Plus => match (l, r) {
+                ("number", "number") => Some("number"),
+                ("string", "string") => Some("string"),
+                _ => None,
+            },
+            TokenType::Minus | TokenType::Star | TokenType::Slash => {
+                match (l, r) {
+                    ("number", "number") => Some("number"),
+                    _ => None,
+                }
+            }
+            TokenType::Greater
+            | TokenType::GreaterEqual
+            | TokenType::Less
+            | TokenType::LessEqual => match (l, r) {
+                ("number", "number") | ("string", "string") => {
+                    Some("bool")
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+        match result {
+            Some(name) => Type::named(name),
+            None if !self.declared(&expr.left)
+                && !self.declared(&expr.right) =>
+            {
+                Type::Any
+            }
+            None => {
+                self.report(
+                    expr.oper.line,
+                    format!(
+                        "Operator '{}' can't be applied to {} and {}.",
+                        expr.oper.lexeme, l, r
+                    ),
+                );
+                Type::Any
+            }
+        }
+    }
+
+    // Whether the type of `expr` comes from an annotation: a variable or
+    // parameter that has one, a call to a function with a declared
+    // return type, or an operator applied to such values
+    fn declared(&mut self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Variable(var) => self
+                .lookup(&var.name.lexeme)
+                .is_some_and(|binding| binding.annotated),
+            Expr::Group(expr) => self.declared(&expr.expr),
+            Expr::Unary(expr) => self.declared(&expr.right),
+            Expr::Binary(expr) => {
+                self.declared(&expr.left) || self.declared(&expr.right)
+            }
+            Expr::Call(call) => match &*call.callee {
+                Expr::Variable(var) => self
+                    .lookup(&var.name.lexeme)
+                    .and_then(|binding| binding.signature.as_ref())
+                    .is_some_and(|signature| {
+                        signature.typed && signature.ret != Type::Any
+                    }),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn call(&mut self, expr: &CallExpr) -> Type {
+        let callee = self.expression(&expr.callee);
+        let args = expr
+            .args
+            .iter()
+            .map(|arg| self.expression(arg))
+            .collect::<Vec<_>>();
+        for (_, arg) in &expr.named {
+            self.expression(arg);
+        }
+        let (name, signature) = match &*expr.callee {
+            // calling a struct makes a record of that struct
+            Expr::Variable(var)
+                if callee.is("struct")
+                    && self.user_types.get(&var.name.lexeme)
+                        == Some(&"struct") =>
+            {
+                return Type::named(&var.name.lexeme);
+            }
+            Expr::Variable(var) => match self.lookup(&var.name.lexeme) {
+                Some(Binding {
+                    signature: Some(signature),
+                    ..
+                }) => (var.name.lexeme.clone(), signature.clone()),
+                _ => return Type::Any,
+            },
+            _ => {
+                if let Type::Named(name) = &callee {
+                    if name != "function"
+                        && name != "struct"
+                        && self.declared(&expr.callee)
+                    {
+                        self.report(
+                            expr.paren.line,
+                            format!(
+                                "Can't call a value of type {}.",
+                                name
+                            ),
+                        );
+                    }
+                }
+                return Type::Any;
+            }
+        };
+        if !signature.typed {
+            return signature.ret;
+        }
+        if args.len() != signature.params.len() {
+            self.report(
+                expr.paren.line,
+                format!(
+                    "{}() expected {} arguments but got {}.",
+                    name,
+                    signature.params.len(),
+                    args.len()
+                ),
+            );
+        } else {
+            for (index, (param, arg)) in
+                signature.params.iter().zip(&args).enumerate()
+            {
+                if !self.accepts(param, arg) {
+                    let what =
+                        format!("argument {} of {}()", index + 1, name);
+                    self.mismatch(expr.paren.line, param, arg, &what);
+                }
+            }
+        }
+        signature.ret
+    }
+}
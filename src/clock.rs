@@ -0,0 +1,80 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// Where the time natives get the time from. Embedders and tests can
+// swap the interpreter's clock for a `FakeClock` to get the same output
+// on every run.
+pub trait Clock {
+    fn now(&self) -> f64; // milliseconds since the Unix epoch
+    fn elapsed(&self) -> f64; // monotonic milliseconds since creation
+    fn sleep(&self, ms: f64);
+}
+
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> SystemClock {
+        SystemClock {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> f64 {
+        match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(time) => time.as_secs_f64() * 1000.0,
+            Err(err) => -err.duration().as_secs_f64() * 1000.0,
+        }
+    }
+
+    fn elapsed(&self) -> f64 {
+        self.start.elapsed().as_secs_f64() * 1000.0
+    }
+
+    // durations too long for a `Duration` sleep for as long as possible
+    fn sleep(&self, ms: f64) {
+        let duration = Duration::try_from_secs_f64(ms / 1000.0);
+        thread::sleep(duration.unwrap_or(Duration::MAX));
+    }
+}
+
+// A clock that only moves when told to. Sleeping advances it instantly.
+// Clones share the same time, so a test can keep one to advance the
+// clock the interpreter is using.
+#[derive(Clone)]
+pub struct FakeClock {
+    now: Rc<Cell<f64>>,
+    start: f64,
+}
+
+impl FakeClock {
+    pub fn new(now: f64) -> FakeClock {
+        FakeClock {
+            now: Rc::new(Cell::new(now)),
+            start: now,
+        }
+    }
+
+    pub fn advance(&self, ms: f64) {
+        self.now.set(self.now.get() + ms);
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> f64 {
+        self.now.get()
+    }
+
+    fn elapsed(&self) -> f64 {
+        self.now.get() - self.start
+    }
+
+    fn sleep(&self, ms: f64) {
+        self.advance(ms);
+    }
+}
@@ -0,0 +1,95 @@
+use crate::functions::NativeFunction;
+use crate::interpreter::Value;
+use crate::stmt::EnumStmt;
+
+use std::rc::Rc;
+
+// enum Shape { Circle(r), Rect(w, h), Empty }
+//
+// Declaring an enum binds its name to the definition. Looking up a
+// variant on it gives a constructor for variants with fields
+// (`Shape.Circle(2)`) and the value itself for those without
+// (`Shape.Empty`).
+#[derive(Debug)]
+pub struct EnumDef {
+    pub name: String,
+    variants: Vec<VariantDef>,
+}
+
+#[derive(Debug)]
+struct VariantDef {
+    name: String,
+    fields: Vec<String>,
+}
+
+// A value made by one of the variants of an enum
+#[derive(Debug)]
+pub struct EnumValue {
+    pub def: Rc<EnumDef>,
+    variant: usize,
+    pub values: Vec<Value>, // one per field of the variant
+}
+
+impl EnumDef {
+    pub fn new(stmt: &EnumStmt) -> EnumDef {
+        EnumDef {
+            name: stmt.name.lexeme.clone(),
+            variants: stmt
+                .variants
+                .iter()
+                .map(|variant| VariantDef {
+                    name: variant.name.lexeme.clone(),
+                    fields: variant
+                        .fields
+                        .iter()
+                        .map(|field| field.lexeme.clone())
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    // `Shape.Circle` or `Shape.Empty`
+    pub fn member(def: &Rc<EnumDef>, name: &str) -> Option<Value> {
+        let variant = def.variants.iter().position(|v| v.name == name)?;
+        let arity = def.variants[variant].fields.len();
+        if arity == 0 {
+            return Some(Value::Variant(Rc::new(EnumValue {
+                def: def.clone(),
+                variant,
+                values: vec![],
+            })));
+        }
+        let owner = def.clone();
+        let name = format!("{}.{}", def.name, name);
+        Some(Value::NativeFunction(NativeFunction::new(
+            &name,
+            arity,
+            move |_intp, args| {
+                Ok(Value::Variant(Rc::new(EnumValue {
+                    def: owner.clone(),
+                    variant,
+                    values: args,
+                })))
+            },
+        )))
+    }
+}
+
+impl EnumValue {
+    pub fn tag(&self) -> &str {
+        &self.def.variants[self.variant].name
+    }
+
+    pub fn field(&self, name: &str) -> Option<Value> {
+        self.def.variants[self.variant]
+            .fields
+            .iter()
+            .position(|field| field == name)
+            .map(|index| self.values[index].clone())
+    }
+
+    pub fn same_variant(&self, other: &EnumValue) -> bool {
+        Rc::ptr_eq(&self.def, &other.def) && self.variant == other.variant
+    }
+}
@@ -1,3 +1,4 @@
+use crate::functions::Function;
 use crate::interpreter::Value;
 use std::error::Error;
 use std::fmt;
@@ -12,6 +13,8 @@ macro_rules! error {
 pub enum LoxError {
     Error(String),
     Return(Value),
+    TailCall(Function, Vec<Value>), // `return f(args);`, see Function::call
+    Exit(i32),                      // raised by `exit()`, unwinds to main
 }
 
 impl Error for LoxError {}
@@ -20,7 +23,10 @@ impl fmt::Display for LoxError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             LoxError::Error(msg) => write!(f, "Error: {}", msg),
-            LoxError::Return(_) => write!(f, "Unreachable code!"),
+            LoxError::Return(_) | LoxError::TailCall(..) => {
+                write!(f, "Unreachable code!")
+            }
+            LoxError::Exit(code) => write!(f, "Exit with code {}", code),
         }
     }
 }
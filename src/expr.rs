@@ -11,6 +11,17 @@ pub enum Expr {
     Assign(AssignExpr),
     Group(GroupExpr),
     Call(CallExpr),
+    OptionalCall(CallExpr), // f?.(a), nil when f is
+    Get(GetExpr),
+    Range(RangeExpr),
+    List(ListExpr),
+    Tuple(TupleExpr),
+    Map(MapExpr),
+    Index(IndexExpr),
+    SetIndex(SetIndexExpr),
+    Is(IsExpr),
+    Set(SetExpr),
+    Coalesce(CoalesceExpr),
     Nil,
 }
 
@@ -19,6 +30,13 @@ pub struct GroupExpr {
     pub expr: Box<Expr>,
 }
 
+// a ?? b, where b is only evaluated when a is nil
+#[derive(Clone, Debug)]
+pub struct CoalesceExpr {
+    pub left: Box<Expr>,
+    pub right: Box<Expr>,
+}
+
 #[derive(Clone, Debug)]
 pub struct BinaryExpr {
     pub left: Box<Expr>,
@@ -53,4 +71,69 @@ pub struct CallExpr {
     pub callee: Box<Expr>,
     pub paren: Token,
     pub args: Vec<Expr>,
+    pub named: Vec<(Token, Expr)>, // f(a, name: b), only for structs
+}
+
+// start..end, start..=end, optionally followed by `step n`
+#[derive(Clone, Debug)]
+pub struct RangeExpr {
+    pub start: Box<Expr>,
+    pub end: Box<Expr>,
+    pub step: Option<Box<Expr>>,
+    pub inclusive: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct ListExpr {
+    pub elements: Vec<Expr>,
+}
+
+// (a, b)
+#[derive(Clone, Debug)]
+pub struct TupleExpr {
+    pub elements: Vec<Expr>,
+}
+
+// {"key": value, other: value}
+#[derive(Clone, Debug)]
+pub struct MapExpr {
+    pub entries: Vec<(String, Expr)>,
+}
+
+// object[index]
+#[derive(Clone, Debug)]
+pub struct IndexExpr {
+    pub object: Box<Expr>,
+    pub bracket: Token,
+    pub index: Box<Expr>,
+}
+
+// object[index] = value
+#[derive(Clone, Debug)]
+pub struct SetIndexExpr {
+    pub object: Box<Expr>,
+    pub bracket: Token,
+    pub index: Box<Expr>,
+    pub value: Box<Expr>,
+}
+
+#[derive(Clone, Debug)]
+pub struct GetExpr {
+    pub object: Box<Expr>,
+    pub name: Token,
+}
+
+// object.name = value
+#[derive(Clone, Debug)]
+pub struct SetExpr {
+    pub object: Box<Expr>,
+    pub name: Token,
+    pub value: Box<Expr>,
+}
+
+// value is number
+#[derive(Clone, Debug)]
+pub struct IsExpr {
+    pub value: Box<Expr>,
+    pub type_name: Token,
 }
@@ -1,9 +1,11 @@
 use crate::environment::Environment;
 use crate::err::LoxError;
+use crate::generator::Generator;
 use crate::interpreter::{Interpreter, Value};
 use crate::stmt::FunStmt;
 
 use std::cell::RefCell;
+use std::fmt;
 use std::rc::Rc;
 
 pub trait Callable {
@@ -17,7 +19,7 @@ pub trait Callable {
 
 #[derive(Clone, Debug)]
 pub struct Function {
-    pub declaration: FunStmt,
+    pub declaration: Rc<FunStmt>, // shared by every copy of the value
     closure: Rc<RefCell<Environment>>, // surrounding environment
 }
 
@@ -27,10 +29,65 @@ impl Function {
         closure: Rc<RefCell<Environment>>,
     ) -> Function {
         Function {
-            declaration,
+            declaration: Rc::new(declaration),
             closure,
         }
     }
+
+    // A `return f(...)` in the body hands back the next function and its
+    // arguments instead of calling it, and this loop runs that call in
+    // place of the current one, so tail recursion doesn't grow the Rust
+    // stack.
+    fn run(
+        &self,
+        intp: &mut Interpreter,
+        args: Vec<Value>,
+    ) -> Result<Value, LoxError> {
+        let mut function = self.clone();
+        let mut args = args;
+        loop {
+            if args.len() != function.arity() {
+                return error!(format!(
+                    "Expected {} arguments but got {}.",
+                    function.arity(),
+                    args.len()
+                ));
+            }
+            let mut env =
+                Environment::with_enclosing(function.closure.clone());
+            function
+                .declaration
+                .params
+                .iter()
+                .zip(args.iter()) // combines two iters into one tuple
+                .for_each(|(name, value)| {
+                    env.define(&name.lexeme, value.clone())
+                });
+
+            let env = Rc::new(RefCell::new(env));
+            if function.declaration.is_generator {
+                // the body only starts running on the first `next()`
+                let generator = Generator::new(
+                    &function.declaration.name.lexeme,
+                    function.declaration.body.clone(),
+                    env,
+                );
+                return Ok(Value::Generator(Rc::new(RefCell::new(
+                    generator,
+                ))));
+            }
+            let result = intp.block(&function.declaration.body, env);
+            match result {
+                Err(LoxError::TailCall(next, next_args)) => {
+                    function = next;
+                    args = next_args;
+                }
+                Err(LoxError::Return(value)) => return Ok(value),
+                Err(err) => return Err(err),
+                Ok(()) => return Ok(Value::Nil),
+            }
+        }
+    }
 }
 
 impl Callable for Function {
@@ -38,35 +95,98 @@ impl Callable for Function {
         self.declaration.params.len()
     }
 
+    // Calls nested deeper than `intp.max_call_depth` fail instead of
+    // overflowing the Rust stack
     fn call(
         &self,
         intp: &mut Interpreter,
         args: Vec<Value>,
     ) -> Result<Value, LoxError> {
-        if args.len() != self.arity() {
+        if intp.call_depth >= intp.max_call_depth {
+            return error!(format!(
+                "Stack overflow at depth {} in function {}.",
+                intp.call_depth, self.declaration.name.lexeme
+            ));
+        }
+        intp.call_depth += 1;
+        let result = self.run(intp, args);
+        intp.call_depth -= 1;
+        result
+    }
+}
+
+type NativeFn =
+    dyn Fn(&mut Interpreter, Vec<Value>) -> Result<Value, LoxError>;
+
+// Functions implemented in Rust and exposed to Lox code
+#[derive(Clone)]
+pub struct NativeFunction {
+    pub name: String,
+    min_arity: usize,
+    max_arity: usize, // trailing arguments past `min_arity` are optional
+    fun: Rc<NativeFn>,
+}
+
+impl NativeFunction {
+    pub fn new<F>(name: &str, arity: usize, fun: F) -> NativeFunction
+    where
+        F: Fn(&mut Interpreter, Vec<Value>) -> Result<Value, LoxError>
+            + 'static,
+    {
+        NativeFunction::with_optional(name, arity, arity, fun)
+    }
+
+    pub fn with_optional<F>(
+        name: &str,
+        min_arity: usize,
+        max_arity: usize,
+        fun: F,
+    ) -> NativeFunction
+    where
+        F: Fn(&mut Interpreter, Vec<Value>) -> Result<Value, LoxError>
+            + 'static,
+    {
+        NativeFunction {
+            name: name.to_owned(),
+            min_arity,
+            max_arity,
+            fun: Rc::new(fun),
+        }
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+impl Callable for NativeFunction {
+    fn arity(&self) -> usize {
+        self.max_arity
+    }
+
+    fn call(
+        &self,
+        intp: &mut Interpreter,
+        args: Vec<Value>,
+    ) -> Result<Value, LoxError> {
+        if args.len() < self.min_arity || args.len() > self.max_arity {
+            let expected = if self.min_arity == self.max_arity {
+                format!("{}", self.min_arity)
+            } else if self.max_arity == usize::MAX {
+                format!("at least {}", self.min_arity)
+            } else {
+                format!("{} to {}", self.min_arity, self.max_arity)
+            };
             error!(format!(
-                "Expected {} arguments but got {}.",
-                self.arity(),
+                "{}() expected {} arguments but got {}.",
+                self.name,
+                expected,
                 args.len()
             ))
         } else {
-            let mut env =
-                Environment::with_enclosing(self.closure.clone());
-            self.declaration
-                .params
-                .iter()
-                .zip(args.iter()) // combines two iters into one tuple
-                .for_each(|(name, value)| {
-                    env.define(&name.lexeme, value.clone())
-                });
-
-            let result = intp
-                .block(&self.declaration.body, Rc::new(RefCell::new(env)));
-            match result {
-                Err(LoxError::Return(value)) => Ok(value),
-                Err(LoxError::Error(msg)) => error!(msg),
-                Ok(()) => Ok(Value::Nil),
-            }
+            (self.fun)(intp, args)
         }
     }
 }
@@ -0,0 +1,272 @@
+use crate::environment::Environment;
+use crate::err::LoxError;
+use crate::functions::NativeFunction;
+use crate::interpreter::{self, Interpreter, Value};
+use crate::iterator::LoxIterator;
+use crate::stmt::{ForInStmt, ForStmt, Stmt, WhileStmt};
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// The interpreter executes statements by recursing on the Rust stack,
+// which can't be paused halfway through. A generator instead keeps an
+// explicit stack of frames for the statements that can contain a
+// `yield` (blocks, ifs and loops), so `next()` can stop at a `yield` and
+// later continue from the same frame. Everything else, including
+// expressions, runs to completion through the regular interpreter.
+#[derive(Debug)]
+pub struct Generator {
+    pub name: String,
+    frames: Vec<Frame>,
+}
+
+// Frames share the statements they run with the function declaration,
+// so resuming a generator doesn't copy any part of its body
+#[derive(Debug)]
+enum Frame {
+    Block {
+        statements: Rc<[Stmt]>,
+        index: usize, // next statement to run
+        env: Rc<RefCell<Environment>>,
+    },
+    While {
+        stmt: Rc<WhileStmt>,
+        env: Rc<RefCell<Environment>>,
+    },
+    ForIn {
+        stmt: Rc<ForInStmt>,
+        iter: LoxIterator,
+        env: Rc<RefCell<Environment>>,
+    },
+    For {
+        stmt: Rc<ForStmt>,
+        env: Rc<RefCell<Environment>>, // holds this iteration's variable
+        enclosing: Rc<RefCell<Environment>>,
+        started: bool, // false until the body has run once
+    },
+}
+
+impl Generator {
+    pub fn new(
+        name: &str,
+        body: Rc<[Stmt]>,
+        env: Rc<RefCell<Environment>>,
+    ) -> Generator {
+        Generator {
+            name: name.to_owned(),
+            frames: vec![Frame::Block {
+                statements: body,
+                index: 0,
+                env,
+            }],
+        }
+    }
+
+    // Runs the body until the next `yield` and returns the yielded
+    // value, or `done` once the body has finished
+    pub fn resume(
+        &mut self,
+        intp: &mut Interpreter,
+    ) -> Result<Value, LoxError> {
+        match self.run(intp) {
+            Ok(Some(value)) => Ok(value),
+            Ok(None) => Ok(Value::Done),
+            Err(err) => {
+                // a failed generator can't be resumed
+                self.frames.clear();
+                Err(err)
+            }
+        }
+    }
+
+    fn run(
+        &mut self,
+        intp: &mut Interpreter,
+    ) -> Result<Option<Value>, LoxError> {
+        while let Some(frame) = self.frames.last_mut() {
+            let yielded = match frame {
+                Frame::Block {
+                    statements,
+                    index,
+                    env,
+                } => {
+                    if *index < statements.len() {
+                        let statements = statements.clone();
+                        let env = env.clone();
+                        let current = *index;
+                        *index += 1;
+                        self.step(intp, &statements[current], env)?
+                    } else {
+                        self.frames.pop();
+                        None
+                    }
+                }
+                Frame::While { stmt, env } => {
+                    let stmt = stmt.clone();
+                    let env = env.clone();
+                    let condition =
+                        intp.evaluate_in(&stmt.condition, env.clone())?;
+                    if condition.into() {
+                        self.step(intp, &stmt.body, env)?
+                    } else {
+                        self.frames.pop();
+                        None
+                    }
+                }
+                Frame::ForIn { stmt, iter, env } => {
+                    match iter.next(intp)? {
+                        Some(item) => {
+                            let stmt = stmt.clone();
+                            let mut env =
+                                Environment::with_enclosing(env.clone());
+                            env.define(&stmt.name.lexeme, item);
+                            let env = Rc::new(RefCell::new(env));
+                            self.step(intp, &stmt.body, env)?
+                        }
+                        None => {
+                            self.frames.pop();
+                            None
+                        }
+                    }
+                }
+                Frame::For {
+                    stmt,
+                    env,
+                    enclosing,
+                    started,
+                } => {
+                    // see `Interpreter::execute_for`
+                    if *started {
+                        // borrowed from the statement, not collected
+                        let names = stmt.loop_vars();
+                        if !names.is_empty() {
+                            *env = interpreter::next_iteration(
+                                enclosing.clone(),
+                                env,
+                                names,
+                            );
+                        }
+                        if let Some(increment) = &stmt.increment {
+                            intp.evaluate_in(increment, env.clone())?;
+                        }
+                    }
+                    *started = true;
+                    let running = match &stmt.condition {
+                        Some(condition) => intp
+                            .evaluate_in(condition, env.clone())?
+                            .into(),
+                        None => true,
+                    };
+                    if running {
+                        let stmt = stmt.clone();
+                        let env = env.clone();
+                        self.step(intp, &stmt.body, env)?
+                    } else {
+                        self.frames.pop();
+                        None
+                    }
+                }
+            };
+            if yielded.is_some() {
+                return Ok(yielded);
+            }
+        }
+        Ok(None)
+    }
+
+    // Runs a statement that isn't made of others, or pushes a frame for
+    // it. Loop bodies and if branches are stepped into directly.
+    fn step(
+        &mut self,
+        intp: &mut Interpreter,
+        statement: &Stmt,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Option<Value>, LoxError> {
+        match statement {
+            Stmt::Yield(stmt) => {
+                let value = match &stmt.value {
+                    Some(expr) => intp.evaluate_in(expr, env)?,
+                    None => Value::Nil,
+                };
+                Ok(Some(value))
+            }
+            Stmt::Block(stmt) => {
+                self.frames.push(Frame::Block {
+                    statements: stmt.statements.clone(),
+                    index: 0,
+                    env: Rc::new(RefCell::new(
+                        Environment::with_enclosing(env),
+                    )),
+                });
+                Ok(None)
+            }
+            Stmt::If(stmt) => {
+                let condition =
+                    intp.evaluate_in(&stmt.condition, env.clone())?;
+                let branch = if condition.into() {
+                    Some(&stmt.then_branch)
+                } else {
+                    stmt.else_branch.as_ref()
+                };
+                match branch {
+                    Some(branch) => self.step(intp, branch, env),
+                    None => Ok(None),
+                }
+            }
+            Stmt::While(stmt) => {
+                self.frames.push(Frame::While {
+                    stmt: stmt.clone(),
+                    env,
+                });
+                Ok(None)
+            }
+            Stmt::ForIn(stmt) => {
+                let iterable =
+                    intp.evaluate_in(&stmt.iterable, env.clone())?;
+                self.frames.push(Frame::ForIn {
+                    stmt: stmt.clone(),
+                    iter: LoxIterator::new(iterable)?,
+                    env,
+                });
+                Ok(None)
+            }
+            Stmt::For(stmt) => {
+                let loop_env = Rc::new(RefCell::new(
+                    Environment::with_enclosing(env.clone()),
+                ));
+                if let Some(init) = &stmt.init {
+                    intp.execute_in(init, loop_env.clone())?;
+                }
+                self.frames.push(Frame::For {
+                    stmt: stmt.clone(),
+                    env: loop_env,
+                    enclosing: env,
+                    started: false,
+                });
+                Ok(None)
+            }
+            Stmt::Return(stmt) => {
+                if let Some(value) = &stmt.value {
+                    intp.evaluate_in(value, env)?;
+                }
+                self.frames.clear();
+                Ok(None)
+            }
+            _ => {
+                intp.execute_in(statement, env)?;
+                Ok(None)
+            }
+        }
+    }
+}
+
+// `gen.next()` is a native function bound to the generator it was
+// looked up on
+pub fn next_method(generator: Rc<RefCell<Generator>>) -> NativeFunction {
+    NativeFunction::new("next", 0, move |intp, _args| {
+        match generator.try_borrow_mut() {
+            Ok(mut generator) => generator.resume(intp),
+            Err(_) => error!("Generator is already running."),
+        }
+    })
+}
@@ -1,18 +1,29 @@
+use crate::clock::{Clock, SystemClock};
+use crate::enums::{EnumDef, EnumValue};
 use crate::environment::Environment;
 use crate::err::LoxError;
 use crate::expr::{
-    AssignExpr, BinaryExpr, CallExpr, Expr, GroupExpr, UnaryExpr,
-    VariableExpr,
+    AssignExpr, BinaryExpr, CallExpr, CoalesceExpr, Expr, GetExpr,
+    GroupExpr, IndexExpr, IsExpr, ListExpr, MapExpr, RangeExpr, SetExpr,
+    SetIndexExpr, TupleExpr, UnaryExpr, VariableExpr,
 };
-use crate::functions::{Callable, Function};
+use crate::functions::{Callable, Function, NativeFunction};
+use crate::generator::{self, Generator};
+use crate::iterator::LoxIterator;
+use crate::map::Map;
+use crate::natives::{self, Rng};
+use crate::range::Range;
 use crate::stmt::{
-    ExpressionStmt, FunStmt, IfStmt, PrintStmt, ReturnStmt, Stmt, VarStmt,
-    WhileStmt,
+    AssertStmt, EnumStmt, ExpressionStmt, ForInStmt, ForStmt, FunStmt,
+    IfStmt, PrintStmt, ReturnStmt, Stmt, StructStmt, VarStmt, WhileStmt,
 };
+use crate::structs::{Record, StructDef};
 use crate::token::{Token, TokenType};
 
 use float_eq::{float_eq, float_ne};
+use regex::Regex;
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::rc::Rc;
 
 #[derive(Clone, Debug)]
@@ -22,8 +33,61 @@ pub enum Value {
     Bool(bool),
     String(String),
     Function(Function),
+    NativeFunction(NativeFunction),
+    Generator(Rc<RefCell<Generator>>),
+    Range(Range),
+    List(Rc<RefCell<Vec<Value>>>),
+    Tuple(Rc<[Value]>), // can't be changed once made
+    Map(Rc<RefCell<Map>>),
+    Enum(Rc<EnumDef>),
+    Variant(Rc<EnumValue>), // a value made by one of an enum's variants
+    Struct(Rc<StructDef>),
+    Record(Rc<Record>), // copied on write, see `Interpreter::update`
+    Done,               // returned by an exhausted generator
 }
 
+// Generates `Value::type_name` and `TYPE_NAMES` from one list of type
+// names and the variants that have them, so the two can't drift apart
+macro_rules! type_names {
+    ($($name:literal => $($variant:ident)|+,)*) => {
+        impl Value {
+            pub fn type_name(&self) -> &'static str {
+                match self {
+                    $($(Value::$variant { .. })|+ => $name,)*
+                }
+            }
+        }
+
+        // Every name `Value::type_name` can return, for checking
+        // `x is name`
+        pub const TYPE_NAMES: &[&str] = &[$($name),*];
+    };
+}
+
+type_names! {
+    "nil" => Nil,
+    "number" => Number,
+    "bool" => Bool,
+    "string" => String,
+    "function" => Function | NativeFunction,
+    "generator" => Generator,
+    "range" => Range,
+    "list" => List,
+    "tuple" => Tuple,
+    "map" => Map,
+    "enum" => Enum | Variant,
+    "struct" => Struct | Record,
+    "done" => Done,
+}
+
+// How many Lox function calls can be nested before a script fails with
+// a stack overflow error instead of crashing
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 10_000;
+
+// How much of the stack an interpreter uses unless told otherwise by
+// `limit_stack()`, little enough for the 2 MiB threads Rust spawns
+const DEFAULT_STACK_LIMIT: usize = 1024 * 1024;
+
 impl From<Value> for bool {
     fn from(value: Value) -> bool {
         match value {
@@ -36,12 +100,50 @@ impl From<Value> for bool {
 
 pub struct Interpreter {
     pub memory: Rc<RefCell<Environment>>,
+    pub rng: Rng, // backs `random()`, reseeded by `seed()`
+    pub allow_fs: bool, // lets scripts read and write files
+    pub allow_run: bool, // lets scripts run other programs
+    pub regex_cache: HashMap<String, Regex>,
+    pub clock: Box<dyn Clock>, // source of time for the time natives
+    pub script_args: Vec<String>, // returned by `args()`
+    pub asserts: bool,         // false with `--no-asserts`
+    pub max_call_depth: usize,
+    pub call_depth: usize, // functions currently being called
+    stack_end: usize,      // the deepest stack address Lox code may reach
 }
 
 impl Interpreter {
     pub fn new() -> Interpreter {
+        let mut globals = Environment::new();
+        natives::define_globals(&mut globals);
         Interpreter {
-            memory: Rc::new(RefCell::new(Environment::new())),
+            memory: Rc::new(RefCell::new(globals)),
+            rng: Rng::from_time(),
+            allow_fs: false,
+            allow_run: false,
+            regex_cache: HashMap::new(),
+            clock: Box::new(SystemClock::new()),
+            script_args: vec![],
+            asserts: true,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            call_depth: 0,
+            stack_end: stack_address().saturating_sub(DEFAULT_STACK_LIMIT),
+        }
+    }
+
+    // Lets Lox code use about `size` bytes of the current thread's stack
+    // from here on. `max_call_depth` alone can't keep scripts within the
+    // stack, since how much a call uses grows with how deeply its body
+    // nests, so execute() and evaluate() also check what is left.
+    pub fn limit_stack(&mut self, size: usize) {
+        self.stack_end = stack_address().saturating_sub(size);
+    }
+
+    fn check_stack(&self) -> Result<(), LoxError> {
+        if stack_address() < self.stack_end {
+            error!("Stack overflow, the code nests too deeply.")
+        } else {
+            Ok(())
         }
     }
 
@@ -56,6 +158,7 @@ impl Interpreter {
     }
 
     fn execute(&mut self, statement: &Stmt) -> Result<(), LoxError> {
+        self.check_stack()?;
         match statement {
             Stmt::Print(stmt) => self.print(stmt),
             Stmt::Expression(stmt) => self.expression(stmt),
@@ -68,8 +171,17 @@ impl Interpreter {
             ),
             Stmt::If(stmt) => self.execute_if(stmt),
             Stmt::While(stmt) => self.execute_while(stmt),
+            Stmt::ForIn(stmt) => self.execute_for_in(stmt),
+            Stmt::For(stmt) => self.execute_for(stmt),
+            Stmt::Assert(stmt) => self.execute_assert(stmt),
+            Stmt::Test(_) => Ok(()), // only run by `rena test`
+            Stmt::Enum(stmt) => self.enum_decl(stmt),
+            Stmt::Struct(stmt) => self.struct_decl(stmt),
             Stmt::Function(stmt) => self.fun_decl(stmt),
             Stmt::Return(stmt) => self.execute_return(stmt),
+            Stmt::Yield(_) => {
+                error!("Can't use 'yield' outside a generator function.")
+            }
         }
     }
 
@@ -77,6 +189,24 @@ impl Interpreter {
         &mut self,
         stmt: &ReturnStmt,
     ) -> Result<(), LoxError> {
+        // named arguments only go to struct constructors, which don't
+        // recurse. The parser only allows `return` in functions, so a
+        // `Function::call` is always there to take the call.
+        let tail_call = match &stmt.value {
+            Some(Expr::Call(call)) if call.named.is_empty() => Some(call),
+            _ => None,
+        };
+        if let Some(call) = tail_call {
+            // a call in tail position is left to the caller's
+            // `Function::call` to run
+            let (callee, args) = self.callee_and_args(call)?;
+            return match callee {
+                Value::Function(fun) => Err(LoxError::TailCall(fun, args)),
+                callee => {
+                    Err(LoxError::Return(self.call_value(callee, args)?))
+                }
+            };
+        }
         if let Some(ref v) = stmt.value {
             let value = self.evaluate(v)?;
             Err(LoxError::Return(value))
@@ -98,6 +228,25 @@ impl Interpreter {
         Ok(())
     }
 
+    fn enum_decl(&mut self, statement: &EnumStmt) -> Result<(), LoxError> {
+        let def = EnumDef::new(statement);
+        self.memory
+            .borrow_mut()
+            .define(&statement.name.lexeme, Value::Enum(Rc::new(def)));
+        Ok(())
+    }
+
+    fn struct_decl(
+        &mut self,
+        statement: &StructStmt,
+    ) -> Result<(), LoxError> {
+        let def = StructDef::new(statement);
+        self.memory
+            .borrow_mut()
+            .define(&statement.name.lexeme, Value::Struct(Rc::new(def)));
+        Ok(())
+    }
+
     fn execute_while(
         &mut self,
         statement: &WhileStmt,
@@ -110,6 +259,93 @@ impl Interpreter {
         Ok(())
     }
 
+    // Like `let` in JavaScript, the loop variable is copied into a fresh
+    // environment before every increment, so closures created in the
+    // body keep the value it had in their iteration
+    fn execute_for(
+        &mut self,
+        statement: &ForStmt,
+    ) -> Result<(), LoxError> {
+        let mut env = Rc::new(RefCell::new(Environment::with_enclosing(
+            self.memory.clone(),
+        )));
+        if let Some(init) = &statement.init {
+            self.execute_in(init, env.clone())?;
+        }
+        let names = statement.loop_vars();
+        loop {
+            if let Some(condition) = &statement.condition {
+                let value = self.evaluate_in(condition, env.clone())?;
+                if !bool::from(value) {
+                    break;
+                }
+            }
+            self.execute_in(&statement.body, env.clone())?;
+            if !names.is_empty() {
+                env = next_iteration(self.memory.clone(), &env, names);
+            }
+            if let Some(increment) = &statement.increment {
+                self.evaluate_in(increment, env.clone())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn execute_for_in(
+        &mut self,
+        statement: &ForInStmt,
+    ) -> Result<(), LoxError> {
+        let iterable = self.evaluate(&statement.iterable)?;
+        let mut iter = LoxIterator::new(iterable)?;
+        while let Some(item) = iter.next(self)? {
+            // every iteration gets a fresh binding, so closures created
+            // in the body capture the current element
+            let mut env = Environment::with_enclosing(self.memory.clone());
+            env.define(&statement.name.lexeme, item);
+            self.execute_in(&statement.body, Rc::new(RefCell::new(env)))?;
+        }
+        Ok(())
+    }
+
+    // The message names the failing condition and, when it is a
+    // comparison, the values on both sides
+    fn execute_assert(
+        &mut self,
+        statement: &AssertStmt,
+    ) -> Result<(), LoxError> {
+        if !self.asserts {
+            return Ok(());
+        }
+        let (passed, operands) = match &statement.condition {
+            Expr::Binary(expr) if is_comparison(expr.oper.token_type) => {
+                let left = self.evaluate(&expr.left)?;
+                let right = self.evaluate(&expr.right)?;
+                let operands = format!(
+                    " (left: {}, right: {})",
+                    self.repr(&left, &mut vec![]),
+                    self.repr(&right, &mut vec![])
+                );
+                let result = self.binary_op(&expr.oper, left, right)?;
+                (bool::from(result), operands)
+            }
+            condition => {
+                (bool::from(self.evaluate(condition)?), String::new())
+            }
+        };
+        if passed {
+            return Ok(());
+        }
+        let mut message = format!(
+            "[line {}] Assertion failed: {}{}",
+            statement.keyword.line, statement.source, operands
+        );
+        if let Some(expr) = &statement.message {
+            let value = self.evaluate(expr)?;
+            message.push_str(&format!(": {}", self.stringify(value)));
+        }
+        error!(message)
+    }
+
     fn execute_if(&mut self, statement: &IfStmt) -> Result<(), LoxError> {
         let value = self.evaluate(&statement.condition)?;
         if value.into() {
@@ -122,22 +358,132 @@ impl Interpreter {
     }
 
     // print 5 + 1;
+    // print "{} + {} = {:.1}", a, b, a + b;
 
     fn print(&mut self, stmt: &PrintStmt) -> Result<(), LoxError> {
         let value = self.evaluate(&stmt.expr)?;
-        println!("{}", self.stringify(value));
+        if stmt.args.is_empty() {
+            println!("{}", self.stringify(value));
+            return Ok(());
+        }
+        let template = match value {
+            Value::String(template) => template,
+            _ => {
+                return error!(format!(
+                    "print expects a format string but got {}.",
+                    value.type_name()
+                ))
+            }
+        };
+        let args = stmt
+            .args
+            .iter()
+            .map(|arg| self.evaluate(arg))
+            .collect::<Result<Vec<_>, _>>()?;
+        println!("{}", natives::format_values(self, &template, &args)?);
         Ok(())
     }
 
-    fn stringify(&self, result: Value) -> String {
+    pub fn stringify(&self, result: Value) -> String {
+        match result {
+            Value::String(value) => value,
+            _ => self.repr(&result, &mut vec![]),
+        }
+    }
+
+    // Like `stringify` but quotes strings, for values nested in lists
+    // and maps. `seen` holds the containers that are being printed, so a
+    // list that contains itself prints as `[...]` instead of looping.
+    fn repr(&self, result: &Value, seen: &mut Vec<*const ()>) -> String {
         match result {
             Value::Number(num) => format!("{num}"),
             Value::Bool(tof) => format!("{tof}"),
-            Value::String(value) => value,
+            Value::String(value) => format!("{:?}", value),
             Value::Nil => "nil".to_string(),
             Value::Function(fun) => {
                 format!("<fn {}>", fun.declaration.name.lexeme)
             }
+            Value::NativeFunction(fun) => {
+                format!("<native fn {}>", fun.name)
+            }
+            Value::Generator(gen) => {
+                format!("<generator {}>", gen.borrow().name)
+            }
+            Value::Range(range) => {
+                let oper = if range.inclusive { "..=" } else { ".." };
+                if float_eq!(range.step, 1.0, ulps <= 10) {
+                    format!("{}{}{}", range.start, oper, range.end)
+                } else {
+                    format!(
+                        "{}{}{} step {}",
+                        range.start, oper, range.end, range.step
+                    )
+                }
+            }
+            Value::List(list) => {
+                let ptr = Rc::as_ptr(list) as *const ();
+                if seen.contains(&ptr) {
+                    return "[...]".to_string();
+                }
+                seen.push(ptr);
+                let elements = list
+                    .borrow()
+                    .iter()
+                    .map(|value| self.repr(value, seen))
+                    .collect::<Vec<_>>();
+                seen.pop();
+                format!("[{}]", elements.join(", "))
+            }
+            Value::Tuple(values) => {
+                let elements = values
+                    .iter()
+                    .map(|value| self.repr(value, seen))
+                    .collect::<Vec<_>>();
+                match elements.len() {
+                    1 => format!("({},)", elements[0]),
+                    _ => format!("({})", elements.join(", ")),
+                }
+            }
+            Value::Map(map) => {
+                let ptr = Rc::as_ptr(map) as *const ();
+                if seen.contains(&ptr) {
+                    return "{...}".to_string();
+                }
+                seen.push(ptr);
+                let entries = map
+                    .borrow()
+                    .iter()
+                    .map(|(key, value)| {
+                        format!("{:?}: {}", key, self.repr(value, seen))
+                    })
+                    .collect::<Vec<_>>();
+                seen.pop();
+                format!("{{{}}}", entries.join(", "))
+            }
+            Value::Enum(def) => format!("<enum {}>", def.name),
+            Value::Variant(value) => {
+                let name = format!("{}.{}", value.def.name, value.tag());
+                if value.values.is_empty() {
+                    return name;
+                }
+                let values = value
+                    .values
+                    .iter()
+                    .map(|value| self.repr(value, seen))
+                    .collect::<Vec<_>>();
+                format!("{}({})", name, values.join(", "))
+            }
+            Value::Struct(def) => format!("<struct {}>", def.name),
+            Value::Record(record) => {
+                let fields = record
+                    .fields()
+                    .map(|(name, value)| {
+                        format!("{}: {}", name, self.repr(value, seen))
+                    })
+                    .collect::<Vec<_>>();
+                format!("{}({})", record.def.name, fields.join(", "))
+            }
+            Value::Done => "done".to_string(),
         }
     }
 
@@ -150,16 +496,36 @@ impl Interpreter {
     }
 
     fn var(&mut self, decl: &VarStmt) -> Result<(), LoxError> {
-        if let Some(init) = &decl.init {
-            let value = self.evaluate(init)?;
-            self.memory.borrow_mut().define(&decl.name.lexeme, value);
-            Ok(())
-        } else {
-            self.memory
-                .borrow_mut()
-                .define(&decl.name.lexeme, Value::Nil);
-            Ok(())
+        let value = match &decl.init {
+            Some(init) => self.evaluate(init)?,
+            None => Value::Nil,
+        };
+        if !decl.unpack {
+            let name = &decl.names[0].lexeme;
+            self.memory.borrow_mut().define(name, value);
+            return Ok(());
         }
+        let values = match value {
+            Value::Tuple(values) => values.to_vec(),
+            Value::List(values) => values.borrow().clone(),
+            _ => {
+                return error!(format!(
+                    "Can only unpack a tuple or list but got {}.",
+                    value.type_name()
+                ))
+            }
+        };
+        if values.len() != decl.names.len() {
+            return error!(format!(
+                "Expected {} values to unpack but got {}.",
+                decl.names.len(),
+                values.len()
+            ));
+        }
+        for (name, value) in decl.names.iter().zip(values) {
+            self.memory.borrow_mut().define(&name.lexeme, value);
+        }
+        Ok(())
     }
 
     pub fn block(
@@ -167,16 +533,32 @@ impl Interpreter {
         statements: &[Stmt],
         env: Rc<RefCell<Environment>>,
     ) -> Result<(), LoxError> {
-        let previous = env.clone();
-        self.memory = env;
-        for stmt in statements {
-            self.execute(stmt).map_err(|err| {
-                self.memory = previous.clone();
-                err
-            })?;
-        }
+        let previous = std::mem::replace(&mut self.memory, env);
+        let result =
+            statements.iter().try_for_each(|stmt| self.execute(stmt));
         self.memory = previous;
-        Ok(())
+        result
+    }
+
+    // used by generators to run a single statement inside one of
+    // their saved environments
+    pub fn execute_in(
+        &mut self,
+        statement: &Stmt,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<(), LoxError> {
+        self.block(std::slice::from_ref(statement), env)
+    }
+
+    pub fn evaluate_in(
+        &mut self,
+        expression: &Expr,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Value, LoxError> {
+        let previous = std::mem::replace(&mut self.memory, env);
+        let result = self.evaluate(expression);
+        self.memory = previous;
+        result
     }
 
     fn variable(
@@ -192,10 +574,7 @@ impl Interpreter {
                 let msg = format!("Undeclared variable '{}'", name.lexeme);
                 error!(msg.as_str())
             }
-            Some(value) => match value {
-                Value::Nil => error!("Uninitialized variable."),
-                _ => Ok(value),
-            },
+            Some(value) => Ok(value),
         }
     }
 
@@ -207,7 +586,9 @@ impl Interpreter {
         match expression.oper.token_type {
             TokenType::Minus => match right {
                 Value::Number(num) => Ok(Value::Number(-num)),
-                _ => error!("Operand not a number."),
+                _ => {
+                    operator_error(&expression.oper, &[right.type_name()])
+                }
             },
             TokenType::Bang => match right {
                 Value::Bool(value) => Ok(Value::Bool(!value)),
@@ -226,6 +607,7 @@ impl Interpreter {
     }
 
     fn evaluate(&mut self, expression: &Expr) -> Result<Value, LoxError> {
+        self.check_stack()?;
         match expression {
             Expr::Nil => Ok(Value::Nil),
             Expr::Number(expr) => Ok(Value::Number(expr.value)),
@@ -237,20 +619,409 @@ impl Interpreter {
             Expr::Group(expr) => self.group(expr),
             Expr::Assign(expr) => self.assignment(expr),
             Expr::Call(expr) => self.call(expr),
+            Expr::OptionalCall(expr) => self.optional_call(expr),
+            Expr::Coalesce(expr) => self.coalesce(expr),
+            Expr::Get(expr) => self.get(expr),
+            Expr::Range(expr) => self.range(expr),
+            Expr::List(expr) => self.list(expr),
+            Expr::Tuple(expr) => self.tuple(expr),
+            Expr::Map(expr) => self.map(expr),
+            Expr::Index(expr) => self.index(expr),
+            Expr::SetIndex(expr) => self.set_index(expr),
+            Expr::Is(expr) => self.is_type(expr),
+            Expr::Set(expr) => self.set(expr),
+        }
+    }
+
+    fn list(&mut self, expr: &ListExpr) -> Result<Value, LoxError> {
+        let elements = expr
+            .elements
+            .iter()
+            .map(|element| self.evaluate(element))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Value::List(Rc::new(RefCell::new(elements))))
+    }
+
+    fn tuple(&mut self, expr: &TupleExpr) -> Result<Value, LoxError> {
+        let elements = expr
+            .elements
+            .iter()
+            .map(|element| self.evaluate(element))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Value::Tuple(elements.into()))
+    }
+
+    fn map(&mut self, expr: &MapExpr) -> Result<Value, LoxError> {
+        let mut map = Map::new();
+        for (key, value) in &expr.entries {
+            let value = self.evaluate(value)?;
+            map.insert(key, value);
+        }
+        Ok(Value::Map(Rc::new(RefCell::new(map))))
+    }
+
+    fn index(&mut self, expr: &IndexExpr) -> Result<Value, LoxError> {
+        let object = self.evaluate(&expr.object)?;
+        let index = self.evaluate(&expr.index)?;
+        match object {
+            Value::List(list) => {
+                let list = list.borrow();
+                let index = check_index(&index, list.len())?;
+                Ok(list[index].clone())
+            }
+            Value::Tuple(values) => {
+                let index = check_index(&index, values.len())?;
+                Ok(values[index].clone())
+            }
+            // walks the string instead of collecting its characters
+            Value::String(value) => {
+                let index = check_index(&index, value.chars().count())?;
+                let c = value.chars().skip(index).take(1).collect();
+                Ok(Value::String(c))
+            }
+            // missing keys read as nil
+            Value::Map(map) => match index {
+                Value::String(key) => Ok(map
+                    .borrow()
+                    .get(&key)
+                    .cloned()
+                    .unwrap_or(Value::Nil)),
+                _ => error!(format!(
+                    "Map keys must be strings but got {}.",
+                    index.type_name()
+                )),
+            },
+            _ => error!(format!(
+                "Can't index into a value of type {}.",
+                object.type_name()
+            )),
+        }
+    }
+
+    fn set_index(
+        &mut self,
+        expr: &SetIndexExpr,
+    ) -> Result<Value, LoxError> {
+        let object = self.evaluate(&expr.object)?;
+        let index = self.evaluate(&expr.index)?;
+        let value = self.evaluate(&expr.value)?;
+        match object {
+            Value::List(list) => {
+                let mut list = list.borrow_mut();
+                let index = check_index(&index, list.len())?;
+                list[index] = value.clone();
+                Ok(value)
+            }
+            Value::Map(map) => match index {
+                Value::String(key) => {
+                    map.borrow_mut().insert(&key, value.clone());
+                    Ok(value)
+                }
+                _ => error!(format!(
+                    "Map keys must be strings but got {}.",
+                    index.type_name()
+                )),
+            },
+            Value::Tuple(_) => error!("Tuples can't be changed."),
+            _ => error!(format!(
+                "Can't assign to an index of a value of type {}.",
+                object.type_name()
+            )),
+        }
+    }
+
+    fn range(&mut self, expr: &RangeExpr) -> Result<Value, LoxError> {
+        let start = self.evaluate(&expr.start)?;
+        let end = self.evaluate(&expr.end)?;
+        let step = match &expr.step {
+            Some(step) => self.evaluate(step)?,
+            None => Value::Number(1.0),
+        };
+        match (start, end, step) {
+            (
+                Value::Number(start),
+                Value::Number(end),
+                Value::Number(step),
+            ) => Ok(Value::Range(Range::new(
+                start,
+                end,
+                step,
+                expr.inclusive,
+            )?)),
+            _ => error!("Range bounds and step must be numbers."),
+        }
+    }
+
+    // `x in 0..10` and `"ell" in "hello"`
+    fn membership(
+        &self,
+        item: Value,
+        container: Value,
+    ) -> Result<Value, LoxError> {
+        match (item, container) {
+            (Value::Number(num), Value::Range(range)) => {
+                Ok(Value::Bool(range.contains(num)))
+            }
+            (Value::String(sub), Value::String(value)) => {
+                Ok(Value::Bool(value.contains(sub.as_str())))
+            }
+            (Value::String(key), Value::Map(map)) => {
+                Ok(Value::Bool(map.borrow().contains_key(&key)))
+            }
+            (item, Value::Tuple(values)) => Ok(Value::Bool(
+                values.iter().any(|value| values_equal(&item, value)),
+            )),
+            (item, Value::List(list)) => Ok(Value::Bool(
+                list.borrow()
+                    .iter()
+                    .any(|value| values_equal(&item, value)),
+            )),
+            (_, Value::Range(_)) => Ok(Value::Bool(false)),
+            (_, container) => error!(format!(
+                "Can't test membership in a value of type {}.",
+                container.type_name()
+            )),
+        }
+    }
+
+    fn is_type(&mut self, expr: &IsExpr) -> Result<Value, LoxError> {
+        let value = self.evaluate(&expr.value)?;
+        let name = expr.type_name.lexeme.as_str();
+        if TYPE_NAMES.contains(&name) {
+            return Ok(Value::Bool(value.type_name() == name));
+        }
+        // otherwise the name of a user defined type
+        match (self.look_up(expr.type_name.clone()), value) {
+            (Ok(Value::Enum(def)), Value::Variant(value)) => {
+                Ok(Value::Bool(Rc::ptr_eq(&def, &value.def)))
+            }
+            (Ok(Value::Struct(def)), Value::Record(record)) => {
+                Ok(Value::Bool(Rc::ptr_eq(&def, &record.def)))
+            }
+            (Ok(Value::Enum(_)), _) | (Ok(Value::Struct(_)), _) => {
+                Ok(Value::Bool(false))
+            }
+            _ => error!(format!("Unknown type '{}'.", name)),
+        }
+    }
+
+    fn get(&mut self, expr: &GetExpr) -> Result<Value, LoxError> {
+        let object = self.evaluate(&expr.object)?;
+        match object {
+            Value::Generator(gen) if expr.name.lexeme == "next" => {
+                Ok(Value::NativeFunction(generator::next_method(gen)))
+            }
+            Value::Map(map)
+                if map.borrow().contains_key(&expr.name.lexeme) =>
+            {
+                Ok(map.borrow().get(&expr.name.lexeme).unwrap().clone())
+            }
+            Value::Enum(def) => EnumDef::member(&def, &expr.name.lexeme)
+                .map_or_else(
+                    || {
+                        error!(format!(
+                            "Enum '{}' has no variant '{}'.",
+                            def.name, expr.name.lexeme
+                        ))
+                    },
+                    Ok,
+                ),
+            Value::Record(record) => record.get(&expr.name.lexeme),
+            // fields shadow `tag`
+            Value::Variant(value) => {
+                match value.field(&expr.name.lexeme) {
+                    Some(field) => Ok(field),
+                    None if expr.name.lexeme == "tag" => {
+                        Ok(Value::String(value.tag().to_owned()))
+                    }
+                    None => error!(format!(
+                        "Variant '{}' has no field '{}'.",
+                        value.tag(),
+                        expr.name.lexeme
+                    )),
+                }
+            }
+            _ => error!(format!(
+                "Undefined property '{}'.",
+                expr.name.lexeme
+            )),
+        }
+    }
+
+    fn set(&mut self, expr: &SetExpr) -> Result<Value, LoxError> {
+        let value = self.evaluate(&expr.value)?;
+        let name = &expr.name.lexeme;
+        self.update(&expr.object, &mut |object| match object {
+            Value::Record(record) => {
+                *Rc::make_mut(record).get_mut(name)? = value.clone();
+                Ok(())
+            }
+            Value::Map(map) => {
+                map.borrow_mut().insert(name, value.clone());
+                Ok(())
+            }
+            _ => error!(format!(
+                "Can't set property '{}' on a value of type {}.",
+                name,
+                object.type_name()
+            )),
+        })?;
+        Ok(value)
+    }
+
+    // Runs `change` on the value stored at `place` and writes it back.
+    // Records are shared until they are changed, so this is what makes
+    // `a.b.x = 1` update the copy held by `a` and nothing else. A place
+    // that isn't stored anywhere, like a call, is changed and dropped.
+    fn update(
+        &mut self,
+        place: &Expr,
+        change: &mut dyn FnMut(&mut Value) -> Result<(), LoxError>,
+    ) -> Result<(), LoxError> {
+        match place {
+            Expr::Variable(var) => {
+                let mut value = self.look_up(var.name.clone())?;
+                change(&mut value)?;
+                self.memory
+                    .borrow_mut()
+                    .assign(&var.name.lexeme, value)?;
+                Ok(())
+            }
+            Expr::Group(group) => self.update(&group.expr, change),
+            Expr::Get(get) => {
+                let name = &get.name.lexeme;
+                self.update(&get.object, &mut |object| match object {
+                    Value::Record(record) => {
+                        change(Rc::make_mut(record).get_mut(name)?)
+                    }
+                    Value::Map(map) => {
+                        let value = map.borrow().get(name).cloned();
+                        match value {
+                            Some(mut value) => {
+                                change(&mut value)?;
+                                map.borrow_mut().insert(name, value);
+                                Ok(())
+                            }
+                            None => error!(format!(
+                                "Undefined property '{}'.",
+                                name
+                            )),
+                        }
+                    }
+                    _ => error!(format!("Undefined property '{}'.", name)),
+                })
+            }
+            Expr::Index(index) => {
+                let key = self.evaluate(&index.index)?;
+                self.update(&index.object, &mut |object| match object {
+                    Value::List(list) => {
+                        let mut list = match list.try_borrow_mut() {
+                            Ok(list) => list,
+                            Err(_) => {
+                                return error!(
+                                    "List is already being changed."
+                                )
+                            }
+                        };
+                        let index = check_index(&key, list.len())?;
+                        change(&mut list[index])
+                    }
+                    Value::Tuple(_) => error!("Tuples can't be changed."),
+                    Value::Map(map) => {
+                        let key = match &key {
+                            Value::String(key) => key,
+                            _ => {
+                                return error!(format!(
+                                    "Map keys must be strings but got {}.",
+                                    key.type_name()
+                                ))
+                            }
+                        };
+                        let mut value = map
+                            .borrow()
+                            .get(key)
+                            .cloned()
+                            .unwrap_or(Value::Nil);
+                        change(&mut value)?;
+                        map.borrow_mut().insert(key, value);
+                        Ok(())
+                    }
+                    _ => error!(format!(
+                        "Can't index a value of type {}.",
+                        object.type_name()
+                    )),
+                })
+            }
+            _ => {
+                let mut value = self.evaluate(place)?;
+                change(&mut value)
+            }
         }
     }
 
     fn call(&mut self, expr: &CallExpr) -> Result<Value, LoxError> {
-        let fun_name = self.evaluate(&expr.callee)?;
-        let args = expr
-            .args
+        let callee = self.evaluate(&expr.callee)?;
+        self.finish_call(callee, expr)
+    }
+
+    // the arguments aren't evaluated when the callee is nil
+    fn optional_call(
+        &mut self,
+        expr: &CallExpr,
+    ) -> Result<Value, LoxError> {
+        match self.evaluate(&expr.callee)? {
+            Value::Nil => Ok(Value::Nil),
+            callee => self.finish_call(callee, expr),
+        }
+    }
+
+    fn finish_call(
+        &mut self,
+        fun_name: Value,
+        expr: &CallExpr,
+    ) -> Result<Value, LoxError> {
+        let args = self.args(expr)?;
+        if expr.named.is_empty() {
+            return self.call_value(fun_name, args);
+        }
+        let named = expr
+            .named
+            .iter()
+            .map(|(name, arg)| {
+                Ok((name.lexeme.clone(), self.evaluate(arg)?))
+            })
+            .collect::<Result<Vec<_>, LoxError>>()?;
+        match fun_name {
+            Value::Struct(def) => StructDef::construct(&def, args, named),
+            _ => error!("Only struct constructors take named arguments."),
+        }
+    }
+
+    fn callee_and_args(
+        &mut self,
+        expr: &CallExpr,
+    ) -> Result<(Value, Vec<Value>), LoxError> {
+        let callee = self.evaluate(&expr.callee)?;
+        Ok((callee, self.args(expr)?))
+    }
+
+    fn args(&mut self, expr: &CallExpr) -> Result<Vec<Value>, LoxError> {
+        expr.args
             .iter() // iterate over the values by reference
             .map(|arg| self.evaluate(arg))
-            .collect::<Result<Vec<_>, _>>()?;
-        if let Value::Function(func) = fun_name {
-            func.call(self, args)
-        } else {
-            error!("Can only call functions and classes.")
+            .collect()
+    }
+
+    pub fn call_value(
+        &mut self,
+        callee: Value,
+        args: Vec<Value>,
+    ) -> Result<Value, LoxError> {
+        match callee {
+            Value::Function(func) => func.call(self, args),
+            Value::NativeFunction(func) => func.call(self, args),
+            Value::Struct(def) => StructDef::construct(&def, args, vec![]),
+            _ => error!("Can only call functions and classes."),
         }
     }
 
@@ -264,6 +1035,16 @@ impl Interpreter {
             .assign(&expression.name.lexeme, value)
     }
 
+    fn coalesce(
+        &mut self,
+        expr: &CoalesceExpr,
+    ) -> Result<Value, LoxError> {
+        match self.evaluate(&expr.left)? {
+            Value::Nil => self.evaluate(&expr.right),
+            value => Ok(value),
+        }
+    }
+
     fn group(
         &mut self,
         expression: &GroupExpr,
@@ -277,10 +1058,26 @@ impl Interpreter {
     ) -> Result<Value, LoxError> {
         let left = self.evaluate(&expression.left)?;
         let right = self.evaluate(&expression.right)?;
+        self.binary_op(&expression.oper, left, right)
+    }
+
+    // Applies a binary operator to values that have already been
+    // evaluated
+    fn binary_op(
+        &self,
+        oper: &Token,
+        left: Value,
+        right: Value,
+    ) -> Result<Value, LoxError> {
+        if oper.token_type == TokenType::In {
+            return self.membership(left, right);
+        }
 
+        let operands = [left.type_name(), right.type_name()];
+        let mismatch = || operator_error(oper, &operands);
         match (left, right) {
             (Value::Number(l), Value::Number(r)) => {
-                match expression.oper.token_type {
+                match oper.token_type {
                     TokenType::EqualEqual => {
                         Ok(Value::Bool(float_eq!(l, r, ulps <= 10)))
                     }
@@ -295,31 +1092,204 @@ impl Interpreter {
                     TokenType::GreaterEqual => Ok(Value::Bool(l >= r)),
                     TokenType::Less => Ok(Value::Bool(l < r)),
                     TokenType::LessEqual => Ok(Value::Bool(l <= r)),
-                    _ => error!("Unknown operation for numbers."),
+                    _ => mismatch(),
                 }
             }
-            (Value::Bool(l), Value::Bool(r)) => {
-                match expression.oper.token_type {
-                    TokenType::EqualEqual => Ok(Value::Bool(l == r)),
-                    TokenType::BangEqual => Ok(Value::Bool(l != r)),
-                    _ => error!("Unknown operation for bools."),
+            (Value::Bool(l), Value::Bool(r)) => match oper.token_type {
+                TokenType::EqualEqual => Ok(Value::Bool(l == r)),
+                TokenType::BangEqual => Ok(Value::Bool(l != r)),
+                _ => mismatch(),
+            },
+            (Value::Range(l), Value::Range(r)) => match oper.token_type {
+                TokenType::EqualEqual => Ok(Value::Bool(l == r)),
+                TokenType::BangEqual => Ok(Value::Bool(l != r)),
+                _ => mismatch(),
+            },
+            (l @ Value::List(_), r @ Value::List(_))
+            | (l @ Value::Tuple(_), r @ Value::Tuple(_))
+            | (l @ Value::Map(_), r @ Value::Map(_))
+            | (l @ Value::Enum(_), r @ Value::Enum(_))
+            | (l @ Value::Variant(_), r @ Value::Variant(_))
+            | (l @ Value::Struct(_), r @ Value::Struct(_))
+            | (l @ Value::Record(_), r @ Value::Record(_)) => {
+                match oper.token_type {
+                    TokenType::EqualEqual => {
+                        Ok(Value::Bool(values_equal(&l, &r)))
+                    }
+                    TokenType::BangEqual => {
+                        Ok(Value::Bool(!values_equal(&l, &r)))
+                    }
+                    _ => mismatch(),
                 }
             }
+            (Value::Nil, Value::Nil) => match oper.token_type {
+                TokenType::EqualEqual => Ok(Value::Bool(true)),
+                TokenType::BangEqual => Ok(Value::Bool(false)),
+                _ => mismatch(),
+            },
+            (Value::Done, Value::Done) => match oper.token_type {
+                TokenType::EqualEqual => Ok(Value::Bool(true)),
+                TokenType::BangEqual => Ok(Value::Bool(false)),
+                _ => mismatch(),
+            },
             (Value::String(l), Value::String(r)) => {
-                match expression.oper.token_type {
+                match oper.token_type {
                     TokenType::EqualEqual => Ok(Value::Bool(l.eq(&r))),
                     TokenType::BangEqual => Ok(Value::Bool(l.ne(&r))),
                     TokenType::Plus => {
                         Ok(Value::String(format!("{l}{r}")))
                     }
-                    _ => error!("Unknown operation for strings."),
+                    // strings compare by their Unicode scalar values
+                    TokenType::Greater => Ok(Value::Bool(l > r)),
+                    TokenType::GreaterEqual => Ok(Value::Bool(l >= r)),
+                    TokenType::Less => Ok(Value::Bool(l < r)),
+                    TokenType::LessEqual => Ok(Value::Bool(l <= r)),
+                    _ => mismatch(),
                 }
             }
-            _ => match expression.oper.token_type {
+            _ => match oper.token_type {
                 TokenType::EqualEqual => Ok(Value::Bool(false)),
                 TokenType::BangEqual => Ok(Value::Bool(true)),
-                _ => error!("Operands should be of same type."),
+                _ => mismatch(),
             },
         }
     }
 }
+
+// A new environment for the next iteration of a `for` loop, holding
+// copies of the loop variables from the current one
+pub fn next_iteration(
+    enclosing: Rc<RefCell<Environment>>,
+    current: &Rc<RefCell<Environment>>,
+    names: &[Token],
+) -> Rc<RefCell<Environment>> {
+    let mut env = Environment::with_enclosing(enclosing);
+    for name in names {
+        if let Some(value) = current.borrow().fetch(&name.lexeme) {
+            env.define(&name.lexeme, value);
+        }
+    }
+    Rc::new(RefCell::new(env))
+}
+
+fn is_comparison(token_type: TokenType) -> bool {
+    matches!(
+        token_type,
+        TokenType::EqualEqual
+            | TokenType::BangEqual
+            | TokenType::Greater
+            | TokenType::GreaterEqual
+            | TokenType::Less
+            | TokenType::LessEqual
+            | TokenType::In
+    )
+}
+
+// "Operator '-' can't be applied to string and number."
+fn operator_error(
+    oper: &Token,
+    operands: &[&str],
+) -> Result<Value, LoxError> {
+    error!(format!(
+        "Operator '{}' can't be applied to {}.",
+        oper.lexeme,
+        operands.join(" and ")
+    ))
+}
+
+// Roughly where the current thread's stack ends, it grows downwards
+fn stack_address() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
+// Structural equality, used for comparing lists and maps
+pub fn values_equal(left: &Value, right: &Value) -> bool {
+    equal(left, right, &mut vec![])
+}
+
+// `seen` holds the pairs of lists and maps that are being compared. A
+// pair that comes up again while comparing itself is a cycle, and is
+// taken to be equal as far as that path goes, so self-referencing
+// values don't recurse forever.
+fn equal(
+    left: &Value,
+    right: &Value,
+    seen: &mut Vec<(*const (), *const ())>,
+) -> bool {
+    match (left, right) {
+        (Value::Nil, Value::Nil) => true,
+        (Value::Number(l), Value::Number(r)) => {
+            float_eq!(*l, *r, ulps <= 10)
+        }
+        (Value::Bool(l), Value::Bool(r)) => l == r,
+        (Value::String(l), Value::String(r)) => l == r,
+        (Value::Range(l), Value::Range(r)) => l == r,
+        (Value::List(l), Value::List(r)) => {
+            let pair = (Rc::as_ptr(l) as *const (), Rc::as_ptr(r) as _);
+            if Rc::ptr_eq(l, r) || seen.contains(&pair) {
+                return true;
+            }
+            seen.push(pair);
+            let (l, r) = (l.borrow(), r.borrow());
+            let result = l.len() == r.len()
+                && l.iter().zip(r.iter()).all(|(l, r)| equal(l, r, seen));
+            seen.pop();
+            result
+        }
+        (Value::Tuple(l), Value::Tuple(r)) => {
+            l.len() == r.len()
+                && l.iter().zip(r.iter()).all(|(l, r)| equal(l, r, seen))
+        }
+        (Value::Map(l), Value::Map(r)) => {
+            let pair = (Rc::as_ptr(l) as *const (), Rc::as_ptr(r) as _);
+            if Rc::ptr_eq(l, r) || seen.contains(&pair) {
+                return true;
+            }
+            seen.push(pair);
+            let (l, r) = (l.borrow(), r.borrow());
+            let result = l.len() == r.len()
+                && l.iter().all(|(key, value)| match r.get(key) {
+                    Some(other) => equal(value, other, seen),
+                    None => false,
+                });
+            seen.pop();
+            result
+        }
+        (Value::Enum(l), Value::Enum(r)) => Rc::ptr_eq(l, r),
+        (Value::Variant(l), Value::Variant(r)) => {
+            l.same_variant(r)
+                && l.values
+                    .iter()
+                    .zip(r.values.iter())
+                    .all(|(l, r)| equal(l, r, seen))
+        }
+        (Value::Struct(l), Value::Struct(r)) => Rc::ptr_eq(l, r),
+        (Value::Record(l), Value::Record(r)) => {
+            Rc::ptr_eq(&l.def, &r.def)
+                && l.fields()
+                    .zip(r.fields())
+                    .all(|((_, l), (_, r))| equal(l, r, seen))
+        }
+        (Value::Done, Value::Done) => true,
+        _ => false,
+    }
+}
+
+// Checks that `index` is a whole number pointing into a sequence of
+// length `len`
+pub fn check_index(index: &Value, len: usize) -> Result<usize, LoxError> {
+    match index {
+        Value::Number(num) if num.fract() != 0.0 => {
+            error!(format!("Index {} is not a whole number.", num))
+        }
+        Value::Number(num) if *num < 0.0 || *num >= len as f64 => error!(
+            format!("Index {} is out of bounds for length {}.", num, len)
+        ),
+        Value::Number(num) => Ok(*num as usize),
+        _ => error!(format!(
+            "Index must be a number but got {}.",
+            index.type_name()
+        )),
+    }
+}
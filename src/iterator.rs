@@ -0,0 +1,109 @@
+use crate::err::LoxError;
+use crate::interpreter::{Interpreter, Value};
+use crate::range::Range;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// The iteration state behind a `for (var x in iterable)` loop
+#[derive(Debug)]
+pub enum LoxIterator {
+    Chars {
+        chars: Vec<char>,
+        index: usize,
+    },
+    Range {
+        range: Range,
+        index: usize,
+    },
+    List {
+        list: Rc<RefCell<Vec<Value>>>,
+        index: usize,
+    },
+    Tuple {
+        values: Rc<[Value]>,
+        index: usize,
+    },
+    // the keys of a map, as they were when the loop started
+    Keys {
+        keys: Vec<String>,
+        index: usize,
+    },
+    // a `next` function that is called until it returns `done`
+    Next(Value),
+}
+
+impl LoxIterator {
+    pub fn new(iterable: Value) -> Result<LoxIterator, LoxError> {
+        match iterable {
+            Value::String(value) => Ok(LoxIterator::Chars {
+                chars: value.chars().collect(),
+                index: 0,
+            }),
+            Value::Range(range) => {
+                Ok(LoxIterator::Range { range, index: 0 })
+            }
+            Value::List(list) => Ok(LoxIterator::List { list, index: 0 }),
+            Value::Tuple(values) => {
+                Ok(LoxIterator::Tuple { values, index: 0 })
+            }
+            Value::Map(map) => Ok(LoxIterator::Keys {
+                keys: map.borrow().keys().cloned().collect(),
+                index: 0,
+            }),
+            Value::Generator(gen) => Ok(LoxIterator::Next(
+                Value::NativeFunction(crate::generator::next_method(gen)),
+            )),
+            Value::Function(_) | Value::NativeFunction(_) => {
+                Ok(LoxIterator::Next(iterable))
+            }
+            _ => error!(format!(
+                "Can't iterate over a value of type {}.",
+                iterable.type_name()
+            )),
+        }
+    }
+
+    pub fn next(
+        &mut self,
+        intp: &mut Interpreter,
+    ) -> Result<Option<Value>, LoxError> {
+        match self {
+            LoxIterator::Chars { chars, index } => {
+                let item = chars
+                    .get(*index)
+                    .map(|c| Value::String(c.to_string()));
+                *index += 1;
+                Ok(item)
+            }
+            LoxIterator::Range { range, index } => {
+                let item = range.get(*index).map(Value::Number);
+                *index += 1;
+                Ok(item)
+            }
+            LoxIterator::Tuple { values, index } => {
+                let item = values.get(*index).cloned();
+                *index += 1;
+                Ok(item)
+            }
+            LoxIterator::List { list, index } => {
+                // the list is read on every step, so it may change
+                // while it is being iterated
+                let item = list.borrow().get(*index).cloned();
+                *index += 1;
+                Ok(item)
+            }
+            LoxIterator::Keys { keys, index } => {
+                let item = keys.get(*index).cloned().map(Value::String);
+                *index += 1;
+                Ok(item)
+            }
+            LoxIterator::Next(fun) => {
+                match intp.call_value(fun.clone(), vec![])? {
+                    Value::Done => Ok(None),
+                    item => Ok(Some(item)),
+                }
+            }
+        }
+    }
+}
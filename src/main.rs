@@ -1,64 +1,269 @@
 #[macro_use]
 mod err;
+mod checker;
+mod clock;
+mod enums;
 mod environment;
 mod expr;
 mod functions;
+mod generator;
 mod interpreter;
+mod iterator;
+mod map;
+mod natives;
 mod parser;
+mod range;
 mod scanner;
 mod stmt;
+mod structs;
+mod test_runner;
 mod token;
 
+use crate::checker::Checker;
+use crate::clock::FakeClock;
 use crate::err::LoxError;
-use crate::interpreter::Interpreter;
+use crate::interpreter::{Interpreter, DEFAULT_MAX_CALL_DEPTH};
+use crate::stmt::Stmt;
 use std::{
     env, fs,
     io::{stdout, Write},
+    process, thread,
 };
 
-fn get_input() -> String {
+// Scripts run on a thread with this much stack, which the interpreter
+// stops using once all but STACK_MARGIN of it is gone. A plain recursive
+// call takes about 7.5 KiB in a debug build, so the default call depth
+// fits, while calls with deeply nested bodies can run out sooner.
+const STACK_SIZE: usize = 128 * 1024 * 1024;
+const STACK_MARGIN: usize = 2 * 1024 * 1024;
+
+// None once stdin is closed
+fn get_input() -> Option<String> {
     let mut input = String::new();
-    input.clear();
     print!("rena$ ");
     let _ = stdout().flush();
-    let _ = std::io::stdin().read_line(&mut input).unwrap();
-    let _ = input.pop();
-
-    input
+    // shares its buffer with the stdin natives, see natives/io.rs
+    match std::io::stdin().read_line(&mut input) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => {
+            let _ = input.pop();
+            Some(input)
+        }
+    }
 }
 
-fn run(src: &str, intp: &mut Interpreter) -> Result<(), LoxError> {
+fn parse(src: &str) -> Result<Vec<Stmt>, LoxError> {
     let tokens = scanner::Scanner::new(src).scan_tokens()?;
-    let ast = parser::Parser::new(tokens).parse()?;
+    parser::Parser::new(tokens).parse()
+}
+
+// Prints every type error in the program, failing if there were any
+fn typecheck(ast: &[Stmt]) -> Result<(), LoxError> {
+    let errors = Checker::new().check(ast);
+    for err in &errors {
+        eprintln!("Type error: {}", err);
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        error!(format!("Found {} type error(s).", errors.len()))
+    }
+}
+
+fn run(
+    src: &str,
+    intp: &mut Interpreter,
+    typecheck_first: bool,
+) -> Result<(), LoxError> {
+    let ast = parse(src)?;
+    if typecheck_first {
+        typecheck(&ast)?;
+    }
     intp.interpret(&ast) // borrowing ast because resolver needs it
 }
 
-fn run_file(path: &str, intp: &mut Interpreter) -> Result<(), LoxError> {
-    let file_string =
-        fs::read_to_string(path).expect("Source file cannot be read.");
-    run(&file_string, intp)
+fn read_source(path: &str) -> Result<String, LoxError> {
+    match fs::read_to_string(path) {
+        Ok(file_string) => Ok(file_string),
+        Err(err) => {
+            error!(format!(
+                "Source file '{}' cannot be read: {}",
+                path, err
+            ))
+        }
+    }
+}
+
+// by the time this is called the interpreter has unwound, so the only
+// thing left to do is flush what the script printed
+fn exit(code: i32) -> ! {
+    let _ = stdout().flush();
+    process::exit(code)
+}
+
+// Settings from the command line, applied to every interpreter that is
+// created
+struct Options {
+    allow_fs: bool,
+    allow_run: bool,
+    asserts: bool,
+    fake_clock: Option<f64>,
+    typecheck_first: bool,
+    max_call_depth: usize,
+}
+
+impl Options {
+    fn interpreter(&self) -> Interpreter {
+        let mut interpreter = Interpreter::new();
+        interpreter.allow_fs = self.allow_fs;
+        interpreter.allow_run = self.allow_run;
+        interpreter.asserts = self.asserts;
+        interpreter.max_call_depth = self.max_call_depth;
+        interpreter.limit_stack(STACK_SIZE - STACK_MARGIN);
+        if let Some(now) = self.fake_clock {
+            interpreter.clock = Box::new(FakeClock::new(now));
+        }
+        interpreter
+    }
 }
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    let mut interpreter = Interpreter::new();
-    if args.len() == 1 {
-        loop {
-            let input = get_input();
+    let mut options = Options {
+        allow_fs: false,
+        allow_run: false,
+        asserts: true,
+        fake_clock: None,
+        typecheck_first: false,
+        max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+    };
+    let mut script = None;
+    let mut args = env::args().skip(1).peekable();
+    // `rena check script.lox` only parses and type checks the script and
+    // `rena test [paths]` runs the test blocks of the scripts in paths
+    let command = match args.peek().map(String::as_str) {
+        Some("check") | Some("test") => args.next(),
+        _ => None,
+    };
+    // options come before the script path, everything after it is
+    // passed on to the script
+    for arg in args.by_ref() {
+        match arg.as_str() {
+            "--allow-fs" => options.allow_fs = true,
+            "--allow-run" => options.allow_run = true,
+            "--typecheck" => options.typecheck_first = true,
+            "--no-asserts" => options.asserts = false,
+            // a clock frozen at the epoch (or at the given timestamp in
+            // milliseconds) for reproducible output
+            "--fake-clock" => options.fake_clock = Some(0.0),
+            _ if arg.starts_with("--fake-clock=") => {
+                match arg["--fake-clock=".len()..].parse::<f64>() {
+                    Ok(now) => options.fake_clock = Some(now),
+                    Err(_) => {
+                        eprintln!("Invalid timestamp in '{}'.", arg);
+                        process::exit(64);
+                    }
+                }
+            }
+            _ if arg.starts_with("--max-call-depth=") => {
+                match arg["--max-call-depth=".len()..].parse::<usize>() {
+                    Ok(depth) if depth > 0 => {
+                        options.max_call_depth = depth
+                    }
+                    _ => {
+                        eprintln!("Invalid call depth in '{}'.", arg);
+                        process::exit(64);
+                    }
+                }
+            }
+            _ if arg.starts_with("--") => {
+                eprintln!("Unknown option '{}'.", arg);
+                process::exit(64);
+            }
+            _ => {
+                script = Some(arg);
+                break;
+            }
+        }
+    }
+    let args: Vec<String> = args.collect();
+    let runner = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || run_command(command, script, args, options));
+    match runner.map(|runner| runner.join()) {
+        Ok(Ok(())) => {}
+        Ok(Err(_)) => exit(101), // the panic message is already printed
+        Err(err) => {
+            eprintln!("Can't start the interpreter: {}", err);
+            exit(70);
+        }
+    }
+}
+
+fn run_command(
+    command: Option<String>,
+    script: Option<String>,
+    args: Vec<String>,
+    options: Options,
+) {
+    match command.as_deref() {
+        Some("check") => {
+            let path = script.unwrap_or_else(|| {
+                eprintln!("Usage: rena check <script>");
+                process::exit(64);
+            });
+            let result = read_source(&path)
+                .and_then(|src| typecheck(&parse(&src)?));
+            if let Err(some_error) = result {
+                eprintln!("{}", some_error);
+                exit(65);
+            }
+        }
+        Some("test") => {
+            let mut paths: Vec<String> = script.into_iter().collect();
+            paths.extend(args);
+            if paths.is_empty() {
+                paths.push("test".to_owned());
+            }
+            let new_interpreter = || options.interpreter();
+            if !test_runner::run_tests(&paths, &new_interpreter) {
+                exit(1);
+            }
+        }
+        _ => run_script(script, args, &options),
+    }
+}
+
+fn run_script(
+    script: Option<String>,
+    args: Vec<String>,
+    options: &Options,
+) {
+    let mut interpreter = options.interpreter();
+    interpreter.script_args = args;
+    let typecheck_first = options.typecheck_first;
+    if let Some(path) = script {
+        // Run a file
+        let result = read_source(&path)
+            .and_then(|src| run(&src, &mut interpreter, typecheck_first));
+        match result {
+            Ok(()) => {}
+            Err(LoxError::Exit(code)) => exit(code),
+            Err(some_error) => {
+                eprintln!("{}", some_error);
+                exit(70);
+            }
+        }
+    } else {
+        while let Some(input) = get_input() {
             if !input.is_empty() {
-                match run(&input, &mut interpreter) {
+                match run(&input, &mut interpreter, typecheck_first) {
                     Ok(()) => continue,
+                    Err(LoxError::Exit(code)) => exit(code),
                     Err(some_error) => eprintln!("{}", some_error),
                 }
             } else {
                 continue;
             }
         }
-    } else {
-        // Run a file
-        match run_file(&args[1], &mut interpreter) {
-            Ok(()) => {}
-            Err(some_error) => eprintln!("{}", some_error),
-        }
     }
 }
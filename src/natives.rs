@@ -0,0 +1,213 @@
+use crate::environment::Environment;
+use crate::err::LoxError;
+use crate::functions::NativeFunction;
+use crate::interpreter::{Interpreter, Value, TYPE_NAMES};
+use crate::range::Range;
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+mod exec;
+mod format;
+mod fs;
+mod io;
+mod json;
+mod math;
+mod patterns;
+mod process;
+mod strings;
+mod time;
+
+pub use format::format_values;
+pub use math::Rng;
+
+// Defines the values and native functions every script starts with
+pub fn define_globals(env: &mut Environment) {
+    env.define("done", Value::Done);
+    define(env, NativeFunction::with_optional("range", 1, 3, range));
+    define(env, NativeFunction::new("len", 1, len));
+    define(env, NativeFunction::new("type", 1, type_of));
+    define_predicates(env);
+    define(env, NativeFunction::new("hash", 1, hash));
+    strings::define_globals(env);
+    format::define_globals(env);
+    math::define_globals(env);
+    fs::define_globals(env);
+    io::define_globals(env);
+    json::define_globals(env);
+    patterns::define_globals(env);
+    time::define_globals(env);
+    process::define_globals(env);
+    exec::define_globals(env);
+}
+
+fn define(env: &mut Environment, fun: NativeFunction) {
+    env.define(&fun.name.clone(), Value::NativeFunction(fun));
+}
+
+pub fn number_arg(fun: &str, value: &Value) -> Result<f64, LoxError> {
+    match value {
+        Value::Number(num) => Ok(*num),
+        _ => error!(format!(
+            "{}() expects a number but got {}.",
+            fun,
+            value.type_name()
+        )),
+    }
+}
+
+pub fn string_arg<'a>(
+    fun: &str,
+    value: &'a Value,
+) -> Result<&'a str, LoxError> {
+    match value {
+        Value::String(value) => Ok(value),
+        _ => error!(format!(
+            "{}() expects a string but got {}.",
+            fun,
+            value.type_name()
+        )),
+    }
+}
+
+// a non-negative whole number, for indices and counts
+pub fn index_arg(fun: &str, value: &Value) -> Result<usize, LoxError> {
+    let num = number_arg(fun, value)?;
+    if num < 0.0 || num.fract() != 0.0 {
+        error!(format!(
+            "{}() expects a non-negative whole number but got {}.",
+            fun, num
+        ))
+    } else {
+        Ok(num as usize)
+    }
+}
+
+// range(end), range(start, end) or range(start, end, step)
+fn range(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let nums = args
+        .iter()
+        .map(|arg| number_arg("range", arg))
+        .collect::<Result<Vec<_>, _>>()?;
+    let range = match nums[..] {
+        [end] => Range::new(0.0, end, 1.0, false),
+        [start, end] => Range::new(start, end, 1.0, false),
+        [start, end, step] => Range::new(start, end, step, false),
+        _ => unreachable!(),
+    }?;
+    Ok(Value::Range(range))
+}
+
+fn len(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    match &args[0] {
+        Value::String(value) => {
+            Ok(Value::Number(value.chars().count() as f64))
+        }
+        Value::Range(range) => Ok(Value::Number(range.len()? as f64)),
+        Value::List(list) => Ok(Value::Number(list.borrow().len() as f64)),
+        Value::Tuple(values) => Ok(Value::Number(values.len() as f64)),
+        Value::Map(map) => Ok(Value::Number(map.borrow().len() as f64)),
+        value => error!(format!(
+            "len() expects a string, list, tuple, map or range but got {}.",
+            value.type_name()
+        )),
+    }
+}
+
+// isNumber(x), isList(x), ... for every builtin type, the same checks
+// as `x is number` in a form that can be passed around as a function
+fn define_predicates(env: &mut Environment) {
+    for &type_name in TYPE_NAMES {
+        let mut name = String::from("is");
+        name.push_str(&type_name[..1].to_uppercase());
+        name.push_str(&type_name[1..]);
+        define(
+            env,
+            NativeFunction::new(&name, 1, move |_intp, args| {
+                Ok(Value::Bool(args[0].type_name() == type_name))
+            }),
+        );
+    }
+}
+
+// type(value) names the same types `x is name` checks for, so values of
+// an enum or struct give its name. Their kind is still checked with
+// `is enum` and `is struct`, which type() never returns for them.
+fn type_of(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    match &args[0] {
+        Value::Variant(value) => Ok(Value::String(value.def.name.clone())),
+        Value::Record(record) => {
+            Ok(Value::String(record.def.name.clone()))
+        }
+        value => Ok(Value::String(value.type_name().to_owned())),
+    }
+}
+
+// hash(value) is the same for values that are exactly equal. `==` lets
+// numbers differ by a rounding error (0.1 + 0.2 == 0.3) but hash() uses
+// their exact bits, so such numbers, and tuples holding them, can hash
+// differently. Only values that can't change have a hash, so lists,
+// maps and records don't.
+fn hash(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let mut hasher = DefaultHasher::new();
+    hash_value(&args[0], &mut hasher)?;
+    // keep it exactly representable as a number
+    Ok(Value::Number((hasher.finish() >> 11) as f64))
+}
+
+fn hash_value(
+    value: &Value,
+    state: &mut DefaultHasher,
+) -> Result<(), LoxError> {
+    value.type_name().hash(state);
+    match value {
+        Value::Nil | Value::Done => {}
+        Value::Number(num) => hash_number(*num, state),
+        Value::Bool(b) => b.hash(state),
+        Value::String(s) => s.hash(state),
+        Value::Range(range) => {
+            hash_number(range.start, state);
+            hash_number(range.end, state);
+            hash_number(range.step, state);
+            range.inclusive.hash(state);
+        }
+        Value::Tuple(values) => {
+            values.len().hash(state);
+            for value in values.iter() {
+                hash_value(value, state)?;
+            }
+        }
+        Value::Variant(value) => {
+            value.def.name.hash(state);
+            value.tag().hash(state);
+            for value in &value.values {
+                hash_value(value, state)?;
+            }
+        }
+        _ => {
+            return error!(format!(
+                "hash() can't hash a value of type {}.",
+                value.type_name()
+            ))
+        }
+    }
+    Ok(())
+}
+
+// -0 == 0, so both need the same hash
+fn hash_number(num: f64, state: &mut DefaultHasher) {
+    let num = if num == 0.0 { 0.0 } else { num };
+    num.to_bits().hash(state);
+}
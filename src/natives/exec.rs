@@ -0,0 +1,237 @@
+// Running other programs, only possible with the interpreter's
+// `allow_run` capability (`--allow-run` on the command line)
+use super::{define, number_arg, string_arg};
+use crate::environment::Environment;
+use crate::err::LoxError;
+use crate::functions::NativeFunction;
+use crate::interpreter::{Interpreter, Value};
+use crate::map::Map;
+
+use std::cell::RefCell;
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub fn define_globals(env: &mut Environment) {
+    define(env, NativeFunction::with_optional("exec", 1, 3, exec));
+}
+
+// exec(cmd, args, options) runs `cmd` directly (not through a shell) and
+// returns {stdout, stderr, status, timedOut}. `status` is nil when the
+// process was killed by a signal or by the timeout.
+//
+// options is a map with any of
+//   input:   string written to the process' stdin
+//   cwd:     working directory
+//   env:     map of variables to set, nil values remove a variable
+//   timeout: milliseconds after which the process is killed
+fn exec(
+    intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    if !intp.allow_run {
+        return error!(
+            "exec() needs permission to run programs, run with --allow-run."
+        );
+    }
+    let program = string_arg("exec", &args[0])?;
+    let mut command = Command::new(program);
+    match args.get(1) {
+        None | Some(Value::Nil) => {}
+        Some(Value::List(list)) => {
+            for arg in list.borrow().iter() {
+                command.arg(string_arg("exec", arg)?);
+            }
+        }
+        Some(value) => {
+            return error!(format!(
+                "exec() expects a list of arguments but got {}.",
+                value.type_name()
+            ))
+        }
+    }
+
+    let mut input = None;
+    let mut timeout = None;
+    match args.get(2) {
+        None | Some(Value::Nil) => {}
+        Some(Value::Map(options)) => {
+            for (key, value) in options.borrow().iter() {
+                match key.as_str() {
+                    "input" => {
+                        input = Some(string_arg("exec", value)?.to_owned())
+                    }
+                    "cwd" => {
+                        command.current_dir(string_arg("exec", value)?);
+                    }
+                    "env" => set_env(&mut command, value)?,
+                    "timeout" => {
+                        let ms = number_arg("exec", value)?;
+                        match Duration::try_from_secs_f64(ms / 1000.0) {
+                            Ok(duration) => timeout = Some(duration),
+                            Err(_) => {
+                                return error!(format!(
+                                    "exec() invalid timeout {}.",
+                                    ms
+                                ))
+                            }
+                        }
+                    }
+                    _ => {
+                        return error!(format!(
+                            "exec() unknown option '{}'.",
+                            key
+                        ))
+                    }
+                }
+            }
+        }
+        Some(value) => {
+            return error!(format!(
+                "exec() expects a map of options but got {}.",
+                value.type_name()
+            ))
+        }
+    }
+
+    let mut child = command
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| {
+            LoxError::Error(format!(
+                "exec() can't run '{}': {}",
+                program, err
+            ))
+        })?;
+
+    // the pipes are fed and drained on their own threads, so a process
+    // filling one pipe while we wait on another can't deadlock us. The
+    // writer is never joined, it ends once the input is written or the
+    // pipe is closed.
+    let stdin = child.stdin.take();
+    thread::spawn(move || {
+        if let (Some(mut stdin), Some(input)) = (stdin, input) {
+            let _ = stdin.write_all(input.as_bytes());
+        }
+    });
+    let stdout = PipeReader::new(child.stdout.take());
+    let stderr = PipeReader::new(child.stderr.take());
+
+    let deadline =
+        timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+    let mut timed_out = false;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) => {}
+            Err(err) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return error!(format!("exec() failed: {}", err));
+            }
+        }
+        if is_past(deadline) {
+            timed_out = true;
+            let _ = child.kill();
+            match child.wait() {
+                Ok(status) => break status,
+                Err(err) => {
+                    return error!(format!("exec() failed: {}", err))
+                }
+            }
+        }
+        thread::sleep(Duration::from_millis(5));
+    };
+
+    // Anything the process started in the background keeps the pipes
+    // open after it is gone, so the output is only waited for until the
+    // deadline, or briefly after a timeout, and the readers are left to
+    // finish on their own.
+    let until = if timed_out {
+        Instant::now().checked_add(Duration::from_millis(100))
+    } else {
+        deadline
+    };
+    let mut result = Map::new();
+    result.insert("stdout", Value::String(stdout.finish(until)));
+    result.insert("stderr", Value::String(stderr.finish(until)));
+    result.insert(
+        "status",
+        match status.code() {
+            Some(code) if !timed_out => Value::Number(code as f64),
+            _ => Value::Nil,
+        },
+    );
+    result.insert("timedOut", Value::Bool(timed_out));
+    Ok(Value::Map(Rc::new(RefCell::new(result))))
+}
+
+fn set_env(command: &mut Command, vars: &Value) -> Result<(), LoxError> {
+    match vars {
+        Value::Map(vars) => {
+            for (name, value) in vars.borrow().iter() {
+                match value {
+                    Value::Nil => command.env_remove(name),
+                    value => command.env(name, string_arg("exec", value)?),
+                };
+            }
+            Ok(())
+        }
+        _ => error!(format!(
+            "exec() env option must be a map but got {}.",
+            vars.type_name()
+        )),
+    }
+}
+
+// whether `deadline` has passed, never when there is none
+fn is_past(deadline: Option<Instant>) -> bool {
+    deadline.is_some_and(|deadline| Instant::now() >= deadline)
+}
+
+// Drains a pipe on its own thread into a buffer that can be taken at
+// any time, even while the thread is still blocked reading
+struct PipeReader {
+    output: Arc<Mutex<Vec<u8>>>,
+    reader: thread::JoinHandle<()>,
+}
+
+impl PipeReader {
+    fn new<R: Read + Send + 'static>(pipe: Option<R>) -> PipeReader {
+        let output = Arc::new(Mutex::new(vec![]));
+        let buffer = output.clone();
+        let reader = thread::spawn(move || {
+            let mut pipe = match pipe {
+                Some(pipe) => pipe,
+                None => return,
+            };
+            let mut chunk = [0; 8192];
+            while let Ok(read @ 1..) = pipe.read(&mut chunk) {
+                if let Ok(mut buffer) = buffer.lock() {
+                    buffer.extend_from_slice(&chunk[..read]);
+                }
+            }
+        });
+        PipeReader { output, reader }
+    }
+
+    // what was read by the end of the pipe or by `until`
+    fn finish(self, until: Option<Instant>) -> String {
+        while !self.reader.is_finished() && !is_past(until) {
+            thread::sleep(Duration::from_millis(5));
+        }
+        match self.output.lock() {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(_) => String::new(),
+        }
+    }
+}
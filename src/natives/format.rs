@@ -0,0 +1,308 @@
+// format(template, ...) and printf(template, ...), also used by
+// `print template, ...`. Placeholders look like `{}`, `{1}` or `{name}`,
+// optionally followed by a spec after a colon:
+//
+//     {:[[fill]align][+][0][width][.precision][type]}
+//
+// where align is `<`, `>` or `^` and type is `x`, `X`, `o`, `b` or `e`.
+// `{name}` is looked up in the map passed as the last argument and `{{`
+// and `}}` stand for literal braces. Every argument has to be used.
+use super::{define, string_arg};
+use crate::environment::Environment;
+use crate::err::LoxError;
+use crate::functions::NativeFunction;
+use crate::interpreter::{Interpreter, Value};
+
+use std::io::{stdout, Write};
+
+// the widest width or precision a spec can ask for
+const MAX_WIDTH: usize = 65535;
+
+pub fn define_globals(env: &mut Environment) {
+    define(
+        env,
+        NativeFunction::with_optional("format", 1, usize::MAX, format),
+    );
+    define(
+        env,
+        NativeFunction::with_optional("printf", 1, usize::MAX, printf),
+    );
+}
+
+fn format(
+    intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let template = string_arg("format", &args[0])?;
+    Ok(Value::String(format_values(intp, template, &args[1..])?))
+}
+
+// Like format() but writes the result without a newline
+fn printf(
+    intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let template = string_arg("printf", &args[0])?;
+    print!("{}", format_values(intp, template, &args[1..])?);
+    let _ = stdout().flush();
+    Ok(Value::Nil)
+}
+
+pub fn format_values(
+    intp: &Interpreter,
+    template: &str,
+    args: &[Value],
+) -> Result<String, LoxError> {
+    let mut out = String::new();
+    let mut chars = template.chars().peekable();
+    let mut next_arg = 0;
+    let mut used = vec![false; args.len()];
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '}' => return error!("Unmatched '}' in format string."),
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => {
+                            return error!(
+                                "Unclosed '{' in format string."
+                            )
+                        }
+                    }
+                }
+                let (name, spec) = match placeholder.find(':') {
+                    Some(colon) => {
+                        (&placeholder[..colon], &placeholder[colon + 1..])
+                    }
+                    None => (placeholder.as_str(), ""),
+                };
+                let value = if name.is_empty() {
+                    next_arg += 1;
+                    positional(args, next_arg - 1, &mut used)?
+                } else if let Ok(index) = name.parse::<usize>() {
+                    positional(args, index, &mut used)?
+                } else {
+                    named(args, name, &mut used)?
+                };
+                out.push_str(&Spec::parse(spec)?.render(intp, value)?);
+            }
+            _ => out.push(c),
+        }
+    }
+    match used.iter().position(|used| !used) {
+        Some(index) => {
+            error!(format!(
+                "Format string doesn't use argument {}.",
+                index
+            ))
+        }
+        None => Ok(out),
+    }
+}
+
+fn positional(
+    args: &[Value],
+    index: usize,
+    used: &mut [bool],
+) -> Result<Value, LoxError> {
+    match args.get(index) {
+        Some(value) => {
+            used[index] = true;
+            Ok(value.clone())
+        }
+        None => error!(format!("No argument {} to format.", index)),
+    }
+}
+
+fn named(
+    args: &[Value],
+    name: &str,
+    used: &mut [bool],
+) -> Result<Value, LoxError> {
+    match args.last() {
+        Some(Value::Map(map)) => match map.borrow().get(name) {
+            Some(value) => {
+                used[args.len() - 1] = true;
+                Ok(value.clone())
+            }
+            None => error!(format!("No value named '{}' to format.", name)),
+        },
+        _ => error!(format!(
+            "Format string uses '{{{}}}' but the last argument isn't a map.",
+            name
+        )),
+    }
+}
+
+struct Spec {
+    fill: char,
+    align: Option<char>,
+    sign: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+    kind: Option<char>,
+}
+
+impl Spec {
+    fn parse(spec: &str) -> Result<Spec, LoxError> {
+        let chars: Vec<char> = spec.chars().collect();
+        let mut result = Spec {
+            fill: ' ',
+            align: None,
+            sign: false,
+            zero: false,
+            width: 0,
+            precision: None,
+            kind: None,
+        };
+        let is_align =
+            |c: Option<&char>| matches!(c, Some('<' | '>' | '^'));
+        let mut i = 0;
+        if is_align(chars.get(1)) {
+            result.fill = chars[0];
+            result.align = Some(chars[1]);
+            i = 2;
+        } else if is_align(chars.first()) {
+            result.align = Some(chars[0]);
+            i = 1;
+        }
+        if chars.get(i) == Some(&'+') {
+            result.sign = true;
+            i += 1;
+        }
+        if chars.get(i) == Some(&'0') {
+            result.zero = true;
+            i += 1;
+        }
+        let (width, next) = digits(&chars, i);
+        if next > i && width.is_none() {
+            return invalid(spec);
+        }
+        result.width = width.unwrap_or(0);
+        i = next;
+        if chars.get(i) == Some(&'.') {
+            let (precision, next) = digits(&chars, i + 1);
+            if precision.is_none() {
+                return invalid(spec);
+            }
+            result.precision = precision;
+            i = next;
+        }
+        if let Some(kind @ ('x' | 'X' | 'o' | 'b' | 'e')) = chars.get(i) {
+            result.kind = Some(*kind);
+            i += 1;
+        }
+        if i < chars.len() {
+            return invalid(spec);
+        }
+        Ok(result)
+    }
+
+    fn render(
+        &self,
+        intp: &Interpreter,
+        value: Value,
+    ) -> Result<String, LoxError> {
+        let num = match value {
+            Value::Number(num) => num,
+            _ if self.sign || self.zero || self.kind.is_some() => {
+                return error!(format!(
+                    "Can't format a value of type {} as a number.",
+                    value.type_name()
+                ))
+            }
+            _ => {
+                let mut text = intp.stringify(value);
+                if let Some(precision) = self.precision {
+                    text = text.chars().take(precision).collect();
+                }
+                return Ok(self.pad("", &text, '<'));
+            }
+        };
+        let body = match self.kind {
+            Some(kind @ ('x' | 'X' | 'o' | 'b')) => {
+                if num.fract() != 0.0 || !num.is_finite() {
+                    return error!(format!(
+                        "Can only format whole numbers as '{}' but got {}.",
+                        kind, num
+                    ));
+                }
+                // 2^64, the first whole number u64 can't hold
+                if num.abs() >= 18446744073709551616.0 {
+                    return error!(format!(
+                        "Can only format numbers below 2^64 as '{}'.",
+                        kind
+                    ));
+                }
+                let whole = num.abs() as u64;
+                match kind {
+                    'x' => format!("{:x}", whole),
+                    'X' => format!("{:X}", whole),
+                    'o' => format!("{:o}", whole),
+                    _ => format!("{:b}", whole),
+                }
+            }
+            Some(_) => match self.precision {
+                Some(precision) => format!("{:.*e}", precision, num.abs()),
+                None => format!("{:e}", num.abs()),
+            },
+            None => match self.precision {
+                Some(precision) => format!("{:.*}", precision, num.abs()),
+                None => format!("{}", num.abs()),
+            },
+        };
+        let sign = if num < 0.0 {
+            "-"
+        } else if self.sign {
+            "+"
+        } else {
+            ""
+        };
+        Ok(self.pad(sign, &body, '>'))
+    }
+
+    // `default` is the alignment when the spec doesn't give one: text
+    // goes left and numbers go right
+    fn pad(&self, sign: &str, body: &str, default: char) -> String {
+        let len = sign.chars().count() + body.chars().count();
+        let missing = self.width.saturating_sub(len);
+        if self.zero && self.align.is_none() {
+            return format!("{}{}{}", sign, "0".repeat(missing), body);
+        }
+        let (before, after) = match self.align.unwrap_or(default) {
+            '<' => (0, missing),
+            '^' => (missing / 2, missing - missing / 2),
+            _ => (missing, 0),
+        };
+        let fill = |n| self.fill.to_string().repeat(n);
+        format!("{}{}{}{}", fill(before), sign, body, fill(after))
+    }
+}
+
+// the number written at `chars[start..]`, if any and not above
+// MAX_WIDTH, and where it ends
+fn digits(chars: &[char], start: usize) -> (Option<usize>, usize) {
+    let mut end = start;
+    while chars.get(end).is_some_and(|c| c.is_ascii_digit()) {
+        end += 1;
+    }
+    let number: String = chars[start..end].iter().collect();
+    let number = number.parse().ok().filter(|&n| n <= MAX_WIDTH);
+    (number, end)
+}
+
+fn invalid<T>(spec: &str) -> Result<T, LoxError> {
+    error!(format!("Invalid format spec '{}'.", spec))
+}
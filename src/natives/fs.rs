@@ -0,0 +1,197 @@
+// File system natives. Anything that touches the disk needs the
+// interpreter's `allow_fs` capability (`--allow-fs` on the command
+// line), the path helpers only work on strings and are always available.
+use super::{define, string_arg};
+use crate::environment::Environment;
+use crate::err::LoxError;
+use crate::functions::NativeFunction;
+use crate::interpreter::{Interpreter, Value};
+
+use std::cell::RefCell;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+pub fn define_globals(env: &mut Environment) {
+    define(env, NativeFunction::new("readFile", 1, read_file));
+    define(env, NativeFunction::new("writeFile", 2, write_file));
+    define(env, NativeFunction::new("appendFile", 2, append_file));
+    define(env, NativeFunction::new("exists", 1, exists));
+    define(env, NativeFunction::new("listDir", 1, list_dir));
+    define(env, NativeFunction::new("mkdir", 1, mkdir));
+    define(env, NativeFunction::new("remove", 1, remove));
+
+    define(
+        env,
+        NativeFunction::with_optional(
+            "joinPath",
+            1,
+            usize::MAX,
+            join_path,
+        ),
+    );
+    define(env, NativeFunction::new("basename", 1, basename));
+    define(env, NativeFunction::new("dirname", 1, dirname));
+    define(env, NativeFunction::new("extension", 1, extension));
+}
+
+fn require_fs(intp: &Interpreter, fun: &str) -> Result<(), LoxError> {
+    if intp.allow_fs {
+        Ok(())
+    } else {
+        error!(format!(
+            "{}() needs file system access, run with --allow-fs.",
+            fun
+        ))
+    }
+}
+
+fn io_error(fun: &str, path: &str, err: io::Error) -> LoxError {
+    LoxError::Error(format!("{}() failed for '{}': {}", fun, path, err))
+}
+
+fn read_file(
+    intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    require_fs(intp, "readFile")?;
+    let path = string_arg("readFile", &args[0])?;
+    fs::read_to_string(path)
+        .map(Value::String)
+        .map_err(|err| io_error("readFile", path, err))
+}
+
+fn write_file(
+    intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    require_fs(intp, "writeFile")?;
+    let path = string_arg("writeFile", &args[0])?;
+    let contents = string_arg("writeFile", &args[1])?;
+    fs::write(path, contents)
+        .map(|_| Value::Nil)
+        .map_err(|err| io_error("writeFile", path, err))
+}
+
+fn append_file(
+    intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    require_fs(intp, "appendFile")?;
+    let path = string_arg("appendFile", &args[0])?;
+    let contents = string_arg("appendFile", &args[1])?;
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .map(|_| Value::Nil)
+        .map_err(|err| io_error("appendFile", path, err))
+}
+
+fn exists(
+    intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    require_fs(intp, "exists")?;
+    let path = string_arg("exists", &args[0])?;
+    Ok(Value::Bool(Path::new(path).exists()))
+}
+
+// the names of the entries in a directory, sorted
+fn list_dir(
+    intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    require_fs(intp, "listDir")?;
+    let path = string_arg("listDir", &args[0])?;
+    let mut names = fs::read_dir(path)
+        .and_then(|entries| {
+            entries
+                .map(|entry| {
+                    entry.map(|entry| {
+                        entry.file_name().to_string_lossy().into_owned()
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|err| io_error("listDir", path, err))?;
+    names.sort();
+    let names = names.into_iter().map(Value::String).collect();
+    Ok(Value::List(Rc::new(RefCell::new(names))))
+}
+
+// creates the directory along with any missing parents
+fn mkdir(
+    intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    require_fs(intp, "mkdir")?;
+    let path = string_arg("mkdir", &args[0])?;
+    fs::create_dir_all(path)
+        .map(|_| Value::Nil)
+        .map_err(|err| io_error("mkdir", path, err))
+}
+
+// removes a file or an empty directory
+fn remove(
+    intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    require_fs(intp, "remove")?;
+    let path = string_arg("remove", &args[0])?;
+    let result = if Path::new(path).is_dir() {
+        fs::remove_dir(path)
+    } else {
+        fs::remove_file(path)
+    };
+    result
+        .map(|_| Value::Nil)
+        .map_err(|err| io_error("remove", path, err))
+}
+
+fn join_path(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let mut path = std::path::PathBuf::new();
+    for arg in &args {
+        path.push(string_arg("joinPath", arg)?);
+    }
+    Ok(Value::String(path.to_string_lossy().into_owned()))
+}
+
+fn basename(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let path = string_arg("basename", &args[0])?;
+    let name = Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned());
+    Ok(Value::String(name.unwrap_or_default()))
+}
+
+fn dirname(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let path = string_arg("dirname", &args[0])?;
+    let parent = Path::new(path)
+        .parent()
+        .map(|parent| parent.to_string_lossy().into_owned());
+    Ok(Value::String(parent.unwrap_or_default()))
+}
+
+// the extension without the dot, or "" when there is none
+fn extension(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let path = string_arg("extension", &args[0])?;
+    let ext = Path::new(path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().into_owned());
+    Ok(Value::String(ext.unwrap_or_default()))
+}
@@ -0,0 +1,66 @@
+// Natives for reading standard input. They all go through the shared
+// `io::stdin()` handle, whose buffer is also used by the REPL prompt, so
+// reading from a script never swallows input meant for the REPL.
+use super::define;
+use crate::environment::Environment;
+use crate::err::LoxError;
+use crate::functions::NativeFunction;
+use crate::interpreter::{Interpreter, Value};
+
+use std::io::{self, Read};
+
+pub fn define_globals(env: &mut Environment) {
+    define(env, NativeFunction::new("readLine", 0, read_line));
+    define(env, NativeFunction::new("readAll", 0, read_all));
+    define(env, NativeFunction::new("lines", 0, lines));
+}
+
+// the next line without its line ending, or None at the end of input
+fn next_line() -> Result<Option<String>, LoxError> {
+    let mut line = String::new();
+    match io::stdin().read_line(&mut line) {
+        Ok(0) => Ok(None),
+        Ok(_) => {
+            if line.ends_with('\n') {
+                line.pop();
+                if line.ends_with('\r') {
+                    line.pop();
+                }
+            }
+            Ok(Some(line))
+        }
+        Err(err) => error!(format!("Cannot read standard input: {}", err)),
+    }
+}
+
+// returns nil once the input is exhausted
+fn read_line(
+    _intp: &mut Interpreter,
+    _args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    Ok(next_line()?.map_or(Value::Nil, Value::String))
+}
+
+fn read_all(
+    _intp: &mut Interpreter,
+    _args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let mut input = String::new();
+    match io::stdin().read_to_string(&mut input) {
+        Ok(_) => Ok(Value::String(input)),
+        Err(err) => error!(format!("Cannot read standard input: {}", err)),
+    }
+}
+
+// an iterator over the remaining lines, read lazily so that
+// `for (var line in lines())` can process input as it arrives
+fn lines(
+    _intp: &mut Interpreter,
+    _args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    Ok(Value::NativeFunction(NativeFunction::new(
+        "lines",
+        0,
+        |_intp, _args| Ok(next_line()?.map_or(Value::Done, Value::String)),
+    )))
+}
@@ -0,0 +1,517 @@
+use super::{define, string_arg};
+use crate::environment::Environment;
+use crate::err::LoxError;
+use crate::functions::NativeFunction;
+use crate::interpreter::{Interpreter, Value};
+use crate::map::Map;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// how deeply arrays and objects can nest, in both directions, so deep
+// documents end in an error instead of overflowing the stack
+const MAX_DEPTH: usize = 512;
+
+// the most spaces jsonStringify() indents by, as in JavaScript
+const MAX_INDENT: f64 = 10.0;
+
+pub fn define_globals(env: &mut Environment) {
+    define(env, NativeFunction::new("jsonParse", 1, json_parse));
+    define(
+        env,
+        NativeFunction::with_optional(
+            "jsonStringify",
+            1,
+            2,
+            json_stringify,
+        ),
+    );
+}
+
+// objects become maps, arrays become lists and null becomes nil
+fn json_parse(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let source = string_arg("jsonParse", &args[0])?;
+    let mut parser = JsonParser {
+        chars: source.chars().collect(),
+        current: 0,
+        depth: 0,
+    };
+    parser.skip_whitespace();
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.is_end() {
+        Ok(value)
+    } else {
+        Err(parser.error("Unexpected data after JSON value."))
+    }
+}
+
+// jsonStringify(value) is compact, jsonStringify(value, indent) puts
+// every element on its own line indented by `indent` spaces, a whole
+// number up to MAX_INDENT, or by the `indent` string
+fn json_stringify(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let indent = match args.get(1) {
+        None | Some(Value::Nil) => None,
+        Some(Value::Number(num))
+            if num.fract() == 0.0 && (0.0..=MAX_INDENT).contains(num) =>
+        {
+            Some(" ".repeat(*num as usize))
+        }
+        Some(Value::Number(num)) => {
+            return error!(format!(
+                "jsonStringify() indent must be a whole number from 0 to \
+                 {} but got {}.",
+                MAX_INDENT, num
+            ))
+        }
+        Some(Value::String(indent)) => Some(indent.clone()),
+        Some(value) => {
+            return error!(format!(
+                "jsonStringify() indent must be a number or string but \
+                 got {}.",
+                value.type_name()
+            ))
+        }
+    };
+    let mut writer = JsonWriter {
+        indent: indent.filter(|indent| !indent.is_empty()),
+        out: String::new(),
+        seen: vec![],
+    };
+    writer.value(&args[0], 0)?;
+    Ok(Value::String(writer.out))
+}
+
+struct JsonParser {
+    chars: Vec<char>,
+    current: usize,
+    depth: usize, // arrays and objects currently being parsed
+}
+
+impl JsonParser {
+    fn is_end(&self) -> bool {
+        self.current >= self.chars.len()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.current).copied()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.current += 1;
+        c
+    }
+
+    // reports the line and column of the current character
+    fn error(&self, message: &str) -> LoxError {
+        let consumed = &self.chars[..self.current.min(self.chars.len())];
+        let line = consumed.iter().filter(|&&c| c == '\n').count() + 1;
+        let column =
+            consumed.iter().rev().take_while(|&&c| c != '\n').count() + 1;
+        LoxError::Error(format!(
+            "jsonParse() error at line {}, column {}: {}",
+            line, column, message
+        ))
+    }
+
+    fn expect(
+        &mut self,
+        expected: char,
+        message: &str,
+    ) -> Result<(), LoxError> {
+        if self.peek() == Some(expected) {
+            self.current += 1;
+            Ok(())
+        } else {
+            Err(self.error(message))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.peek() {
+            self.current += 1;
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, LoxError> {
+        match self.peek() {
+            Some(c @ ('{' | '[')) => {
+                if self.depth == MAX_DEPTH {
+                    return Err(self.error(&format!(
+                        "Nested deeper than {} levels.",
+                        MAX_DEPTH
+                    )));
+                }
+                self.depth += 1;
+                let value = if c == '{' {
+                    self.object()
+                } else {
+                    self.array()
+                };
+                self.depth -= 1;
+                value
+            }
+            Some('"') => Ok(Value::String(self.string()?)),
+            Some('-' | '0'..='9') => self.number(),
+            Some('t') => self.literal("true", Value::Bool(true)),
+            Some('f') => self.literal("false", Value::Bool(false)),
+            Some('n') => self.literal("null", Value::Nil),
+            Some(_) => Err(self.error("Expect a JSON value.")),
+            None => Err(self.error("Unexpected end of input.")),
+        }
+    }
+
+    fn literal(
+        &mut self,
+        word: &str,
+        value: Value,
+    ) -> Result<Value, LoxError> {
+        for expected in word.chars() {
+            if self.peek() != Some(expected) {
+                return Err(self.error(&format!("Expect '{}'.", word)));
+            }
+            self.current += 1;
+        }
+        Ok(value)
+    }
+
+    fn object(&mut self) -> Result<Value, LoxError> {
+        self.current += 1; // opening brace
+        let mut map = Map::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.current += 1;
+            return Ok(Value::Map(Rc::new(RefCell::new(map))));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("Expect string as object key."));
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(':', "Expect ':' after object key.")?;
+            self.skip_whitespace();
+            let value = self.value()?;
+            map.insert(&key, value);
+            self.skip_whitespace();
+            match self.advance() {
+                Some(',') => continue,
+                Some('}') => break,
+                _ => {
+                    self.current -= 1;
+                    return Err(self.error("Expect ',' or '}' in object."));
+                }
+            }
+        }
+        Ok(Value::Map(Rc::new(RefCell::new(map))))
+    }
+
+    fn array(&mut self) -> Result<Value, LoxError> {
+        self.current += 1; // opening bracket
+        let mut elements = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.current += 1;
+            return Ok(Value::List(Rc::new(RefCell::new(elements))));
+        }
+        loop {
+            self.skip_whitespace();
+            elements.push(self.value()?);
+            self.skip_whitespace();
+            match self.advance() {
+                Some(',') => continue,
+                Some(']') => break,
+                _ => {
+                    self.current -= 1;
+                    return Err(self.error("Expect ',' or ']' in array."));
+                }
+            }
+        }
+        Ok(Value::List(Rc::new(RefCell::new(elements))))
+    }
+
+    fn string(&mut self) -> Result<String, LoxError> {
+        self.current += 1; // opening quote
+        let mut value = String::new();
+        loop {
+            match self.advance() {
+                Some('"') => return Ok(value),
+                Some('\\') => value.push(self.escape()?),
+                Some(c) if (c as u32) < 0x20 => {
+                    self.current -= 1;
+                    return Err(self.error("Control character in string."));
+                }
+                Some(c) => value.push(c),
+                None => return Err(self.error("Unterminated string.")),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, LoxError> {
+        match self.advance() {
+            Some('"') => Ok('"'),
+            Some('\\') => Ok('\\'),
+            Some('/') => Ok('/'),
+            Some('b') => Ok('\u{8}'),
+            Some('f') => Ok('\u{c}'),
+            Some('n') => Ok('\n'),
+            Some('r') => Ok('\r'),
+            Some('t') => Ok('\t'),
+            Some('u') => {
+                let high = self.hex_code()?;
+                if !(0xd800..0xdc00).contains(&high) {
+                    return char::from_u32(high)
+                        .ok_or_else(|| self.error("Invalid \\u escape."));
+                }
+                // characters outside the BMP are written as a pair of
+                // UTF-16 surrogates
+                if self.advance() != Some('\\')
+                    || self.advance() != Some('u')
+                {
+                    return Err(self.error(
+                        "Expect low surrogate after high surrogate.",
+                    ));
+                }
+                let low = self.hex_code()?;
+                if !(0xdc00..0xe000).contains(&low) {
+                    return Err(self.error("Invalid low surrogate."));
+                }
+                let code =
+                    0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00);
+                char::from_u32(code)
+                    .ok_or_else(|| self.error("Invalid \\u escape."))
+            }
+            _ => {
+                self.current -= 1;
+                Err(self.error("Invalid escape sequence."))
+            }
+        }
+    }
+
+    fn hex_code(&mut self) -> Result<u32, LoxError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            match self.peek().and_then(|c| c.to_digit(16)) {
+                Some(digit) => code = code * 16 + digit,
+                None => {
+                    return Err(
+                        self.error("Expect 4 hex digits in \\u escape.")
+                    )
+                }
+            }
+            self.current += 1;
+        }
+        Ok(code)
+    }
+
+    fn number(&mut self) -> Result<Value, LoxError> {
+        let start = self.current;
+        if self.peek() == Some('-') {
+            self.current += 1;
+        }
+        self.digits()?;
+        if self.peek() == Some('.') {
+            self.current += 1;
+            self.digits()?;
+        }
+        if let Some('e' | 'E') = self.peek() {
+            self.current += 1;
+            if let Some('+' | '-') = self.peek() {
+                self.current += 1;
+            }
+            self.digits()?;
+        }
+        let text: String =
+            self.chars[start..self.current].iter().collect();
+        text.parse::<f64>()
+            .map(Value::Number)
+            .map_err(|_| self.error("Invalid number."))
+    }
+
+    fn digits(&mut self) -> Result<(), LoxError> {
+        let start = self.current;
+        while let Some('0'..='9') = self.peek() {
+            self.current += 1;
+        }
+        if self.current == start {
+            Err(self.error("Expect digit."))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+// jsonStringify(0..n) writes every number of the range, so very long
+// ranges are refused instead of filling memory
+const MAX_RANGE_LEN: usize = 10_000_000;
+
+struct JsonWriter {
+    indent: Option<String>,
+    out: String,
+    // lists and maps that are being written, to detect cycles
+    seen: Vec<*const ()>,
+}
+
+impl JsonWriter {
+    fn value(
+        &mut self,
+        value: &Value,
+        depth: usize,
+    ) -> Result<(), LoxError> {
+        match value {
+            Value::Nil => self.out.push_str("null"),
+            Value::Bool(b) => self.out.push_str(&b.to_string()),
+            Value::Number(num) => {
+                if !num.is_finite() {
+                    return error!(format!(
+                        "jsonStringify() can't represent the number {}.",
+                        num
+                    ));
+                }
+                self.out.push_str(&num.to_string());
+            }
+            Value::String(value) => self.string(value),
+            Value::List(list) => {
+                let ptr = Rc::as_ptr(list) as *const ();
+                nest(depth)?;
+                self.enter(ptr)?;
+                self.array(list.borrow().iter(), depth)?;
+                self.seen.pop();
+            }
+            Value::Map(map) => {
+                let ptr = Rc::as_ptr(map) as *const ();
+                nest(depth)?;
+                self.enter(ptr)?;
+                let map = map.borrow();
+                self.out.push('{');
+                for (i, (key, value)) in map.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push(',');
+                    }
+                    self.newline(depth + 1);
+                    self.string(key);
+                    self.out.push(':');
+                    if self.indent.is_some() {
+                        self.out.push(' ');
+                    }
+                    self.value(value, depth + 1)?;
+                }
+                if map.len() > 0 {
+                    self.newline(depth);
+                }
+                self.out.push('}');
+                self.seen.pop();
+            }
+            // tuples become arrays
+            Value::Tuple(values) => {
+                nest(depth)?;
+                self.array(values.iter(), depth)?
+            }
+            Value::Range(range) => {
+                let len = match range.len() {
+                    Ok(len) if len <= MAX_RANGE_LEN => len,
+                    _ => {
+                        return error!(format!(
+                            "jsonStringify() can't serialize a range of \
+                             more than {} numbers.",
+                            MAX_RANGE_LEN
+                        ))
+                    }
+                };
+                let numbers = (0..len)
+                    .filter_map(|i| range.get(i))
+                    .map(Value::Number);
+                self.array(numbers, depth)?;
+            }
+            _ => {
+                return error!(format!(
+                    "jsonStringify() can't serialize a {}.",
+                    value.type_name()
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    // writes the elements one by one, without collecting them first
+    fn array<I>(
+        &mut self,
+        elements: I,
+        depth: usize,
+    ) -> Result<(), LoxError>
+    where
+        I: Iterator,
+        I::Item: std::borrow::Borrow<Value>,
+    {
+        self.out.push('[');
+        let mut empty = true;
+        for element in elements {
+            if !empty {
+                self.out.push(',');
+            }
+            empty = false;
+            self.newline(depth + 1);
+            self.value(std::borrow::Borrow::borrow(&element), depth + 1)?;
+        }
+        if !empty {
+            self.newline(depth);
+        }
+        self.out.push(']');
+        Ok(())
+    }
+
+    fn enter(&mut self, ptr: *const ()) -> Result<(), LoxError> {
+        if self.seen.contains(&ptr) {
+            error!("jsonStringify() can't serialize a cyclic structure.")
+        } else {
+            self.seen.push(ptr);
+            Ok(())
+        }
+    }
+
+    fn newline(&mut self, depth: usize) {
+        if let Some(indent) = &self.indent {
+            self.out.push('\n');
+            self.out.push_str(&indent.repeat(depth));
+        }
+    }
+
+    fn string(&mut self, value: &str) {
+        self.out.push('"');
+        for c in value.chars() {
+            match c {
+                '"' => self.out.push_str("\\\""),
+                '\\' => self.out.push_str("\\\\"),
+                '\n' => self.out.push_str("\\n"),
+                '\r' => self.out.push_str("\\r"),
+                '\t' => self.out.push_str("\\t"),
+                c if (c as u32) < 0x20 => {
+                    self.out.push_str(&format!("\\u{:04x}", c as u32))
+                }
+                c => self.out.push(c),
+            }
+        }
+        self.out.push('"');
+    }
+}
+
+// checks that an array or object can start at `depth`
+fn nest(depth: usize) -> Result<(), LoxError> {
+    if depth < MAX_DEPTH {
+        Ok(())
+    } else {
+        error!(format!(
+            "jsonStringify() can't serialize values nested more than {} \
+             levels deep.",
+            MAX_DEPTH
+        ))
+    }
+}
@@ -0,0 +1,209 @@
+use super::{define, number_arg};
+use crate::environment::Environment;
+use crate::err::LoxError;
+use crate::functions::NativeFunction;
+use crate::interpreter::{Interpreter, Value};
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub fn define_globals(env: &mut Environment) {
+    env.define("PI", Value::Number(std::f64::consts::PI));
+    env.define("E", Value::Number(std::f64::consts::E));
+    env.define("INF", Value::Number(f64::INFINITY));
+    env.define("NAN", Value::Number(f64::NAN));
+
+    define_unary(env, "sqrt", f64::sqrt);
+    define_unary(env, "abs", f64::abs);
+    define_unary(env, "floor", f64::floor);
+    define_unary(env, "ceil", f64::ceil);
+    define_unary(env, "round", f64::round);
+    define_unary(env, "sin", f64::sin);
+    define_unary(env, "cos", f64::cos);
+    define_unary(env, "tan", f64::tan);
+    define_unary(env, "asin", f64::asin);
+    define_unary(env, "acos", f64::acos);
+    define_unary(env, "atan", f64::atan);
+    define_unary(env, "exp", f64::exp);
+    define_unary(env, "log10", f64::log10);
+    define_unary(env, "log2", f64::log2);
+    define(env, NativeFunction::new("pow", 2, pow));
+    define(env, NativeFunction::new("atan2", 2, atan2));
+    define(env, NativeFunction::new("divmod", 2, divmod));
+    define(env, NativeFunction::with_optional("log", 1, 2, log));
+    define(
+        env,
+        NativeFunction::with_optional("min", 1, usize::MAX, min),
+    );
+    define(
+        env,
+        NativeFunction::with_optional("max", 1, usize::MAX, max),
+    );
+
+    define(env, NativeFunction::new("random", 0, random));
+    define(env, NativeFunction::new("randomInt", 2, random_int));
+    define(env, NativeFunction::new("seed", 1, seed));
+}
+
+fn define_unary(
+    env: &mut Environment,
+    name: &'static str,
+    op: fn(f64) -> f64,
+) {
+    define(
+        env,
+        NativeFunction::new(name, 1, move |_intp, args| {
+            Ok(Value::Number(op(number_arg(name, &args[0])?)))
+        }),
+    );
+}
+
+fn pow(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let base = number_arg("pow", &args[0])?;
+    let exponent = number_arg("pow", &args[1])?;
+    Ok(Value::Number(base.powf(exponent)))
+}
+
+fn atan2(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let y = number_arg("atan2", &args[0])?;
+    let x = number_arg("atan2", &args[1])?;
+    Ok(Value::Number(y.atan2(x)))
+}
+
+// divmod(a, b) is `(q, r)` where q is a / b rounded down and
+// a == q * b + r, so r has the sign of b
+fn divmod(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let a = number_arg("divmod", &args[0])?;
+    let b = number_arg("divmod", &args[1])?;
+    if b == 0.0 {
+        return error!("divmod() can't divide by zero.");
+    }
+    let quotient = (a / b).floor();
+    let remainder = a - quotient * b;
+    Ok(Value::Tuple(
+        vec![Value::Number(quotient), Value::Number(remainder)].into(),
+    ))
+}
+
+// log(x) is the natural logarithm, log(x, base) uses the given base
+fn log(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let num = number_arg("log", &args[0])?;
+    match args.get(1) {
+        Some(base) => Ok(Value::Number(num.log(number_arg("log", base)?))),
+        None => Ok(Value::Number(num.ln())),
+    }
+}
+
+// min(a, b, ...) or min(list)
+fn min(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let nums = numbers("min", args)?;
+    Ok(Value::Number(
+        nums.into_iter().fold(f64::INFINITY, f64::min),
+    ))
+}
+
+fn max(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let nums = numbers("max", args)?;
+    Ok(Value::Number(
+        nums.into_iter().fold(f64::NEG_INFINITY, f64::max),
+    ))
+}
+
+// the arguments of a variadic function, which may also be passed as a
+// single list
+fn numbers(fun: &str, args: Vec<Value>) -> Result<Vec<f64>, LoxError> {
+    let values = match &args[..] {
+        [Value::List(list)] => list.borrow().clone(),
+        _ => args,
+    };
+    if values.is_empty() {
+        return error!(format!("{}() of an empty list.", fun));
+    }
+    values.iter().map(|value| number_arg(fun, value)).collect()
+}
+
+// a number in [0, 1)
+fn random(
+    intp: &mut Interpreter,
+    _args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    Ok(Value::Number(intp.rng.next_f64()))
+}
+
+// a whole number between `low` and `high`, both inclusive
+fn random_int(
+    intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let low = number_arg("randomInt", &args[0])?.ceil();
+    let high = number_arg("randomInt", &args[1])?.floor();
+    if low > high {
+        error!(format!(
+            "randomInt() range {}..={} contains no whole numbers.",
+            low, high
+        ))
+    } else {
+        let offset = (intp.rng.next_f64() * (high - low + 1.0)).floor();
+        Ok(Value::Number(low + offset))
+    }
+}
+
+fn seed(
+    intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let seed = number_arg("seed", &args[0])?;
+    intp.rng = Rng::new(seed.to_bits());
+    Ok(Value::Nil)
+}
+
+// SplitMix64, small and good enough for scripts. Seeding it with the
+// same number always produces the same sequence on every platform.
+#[derive(Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    pub fn from_time() -> Rng {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_nanos() as u64)
+            .unwrap_or(0);
+        Rng::new(nanos)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        // the top 53 bits fill the mantissa of a double exactly
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
@@ -0,0 +1,135 @@
+// Regular expression natives. Positions in results are character
+// indices, like everywhere else strings are indexed.
+use super::{define, string_arg};
+use crate::environment::Environment;
+use crate::err::LoxError;
+use crate::functions::NativeFunction;
+use crate::interpreter::{Interpreter, Value};
+use crate::map::Map;
+
+use regex::{Captures, Regex};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// compiled patterns are cached on the interpreter, up to this many
+const CACHE_LIMIT: usize = 256;
+
+pub fn define_globals(env: &mut Environment) {
+    define(env, NativeFunction::new("regexMatch", 2, regex_match));
+    define(env, NativeFunction::new("regexFind", 2, regex_find));
+    define(env, NativeFunction::new("regexFindAll", 2, regex_find_all));
+    define(env, NativeFunction::new("regexReplace", 3, regex_replace));
+}
+
+fn compile(
+    intp: &mut Interpreter,
+    fun: &str,
+    pattern: &str,
+) -> Result<Regex, LoxError> {
+    if let Some(regex) = intp.regex_cache.get(pattern) {
+        return Ok(regex.clone());
+    }
+    // regex-syntax reports where in the pattern the problem is, which
+    // the error from `Regex::new` only shows as an ASCII drawing
+    if let Err(err) = regex_syntax::Parser::new().parse(pattern) {
+        let (offset, kind) = match &err {
+            regex_syntax::Error::Parse(err) => {
+                (err.span().start.offset, err.kind().to_string())
+            }
+            regex_syntax::Error::Translate(err) => {
+                (err.span().start.offset, err.kind().to_string())
+            }
+            _ => (0, err.to_string()),
+        };
+        let position = pattern[..offset].chars().count();
+        return error!(format!(
+            "{}() invalid pattern at position {}: {}.",
+            fun, position, kind
+        ));
+    }
+    let regex = Regex::new(pattern).map_err(|err| {
+        LoxError::Error(format!("{}() invalid pattern: {}", fun, err))
+    })?;
+    if intp.regex_cache.len() >= CACHE_LIMIT {
+        intp.regex_cache.clear();
+    }
+    intp.regex_cache.insert(pattern.to_owned(), regex.clone());
+    Ok(regex)
+}
+
+// whether the pattern matches anywhere in the text
+fn regex_match(
+    intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let pattern = string_arg("regexMatch", &args[0])?;
+    let text = string_arg("regexMatch", &args[1])?;
+    let regex = compile(intp, "regexMatch", pattern)?;
+    Ok(Value::Bool(regex.is_match(text)))
+}
+
+// the first match, or nil
+fn regex_find(
+    intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let pattern = string_arg("regexFind", &args[0])?;
+    let text = string_arg("regexFind", &args[1])?;
+    let regex = compile(intp, "regexFind", pattern)?;
+    Ok(regex
+        .captures(text)
+        .map_or(Value::Nil, |caps| match_value(&regex, text, &caps)))
+}
+
+fn regex_find_all(
+    intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let pattern = string_arg("regexFindAll", &args[0])?;
+    let text = string_arg("regexFindAll", &args[1])?;
+    let regex = compile(intp, "regexFindAll", pattern)?;
+    let matches = regex
+        .captures_iter(text)
+        .map(|caps| match_value(&regex, text, &caps))
+        .collect();
+    Ok(Value::List(Rc::new(RefCell::new(matches))))
+}
+
+// replaces every match, `$1` or `${name}` in the replacement refer to
+// capture groups
+fn regex_replace(
+    intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let pattern = string_arg("regexReplace", &args[0])?;
+    let text = string_arg("regexReplace", &args[1])?;
+    let replacement = string_arg("regexReplace", &args[2])?;
+    let regex = compile(intp, "regexReplace", pattern)?;
+    Ok(Value::String(
+        regex.replace_all(text, replacement).into_owned(),
+    ))
+}
+
+// {match: "...", start: 0, end: 3, groups: [...], named: {...}}
+// where groups that didn't participate in the match are nil
+fn match_value(regex: &Regex, text: &str, caps: &Captures) -> Value {
+    let whole = caps.get(0).expect("Group 0 is always the whole match.");
+    let char_index = |byte: usize| text[..byte].chars().count() as f64;
+    let group = |m: Option<regex::Match>| {
+        m.map_or(Value::Nil, |m| Value::String(m.as_str().to_owned()))
+    };
+
+    let groups = caps.iter().skip(1).map(group).collect();
+    let mut named = Map::new();
+    for name in regex.capture_names().flatten() {
+        named.insert(name, group(caps.name(name)));
+    }
+
+    let mut result = Map::new();
+    result.insert("match", Value::String(whole.as_str().to_owned()));
+    result.insert("start", Value::Number(char_index(whole.start())));
+    result.insert("end", Value::Number(char_index(whole.end())));
+    result.insert("groups", Value::List(Rc::new(RefCell::new(groups))));
+    result.insert("named", Value::Map(Rc::new(RefCell::new(named))));
+    Value::Map(Rc::new(RefCell::new(result)))
+}
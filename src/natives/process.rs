@@ -0,0 +1,85 @@
+use super::{define, number_arg, string_arg};
+use crate::environment::Environment;
+use crate::err::LoxError;
+use crate::functions::NativeFunction;
+use crate::interpreter::{Interpreter, Value};
+
+use std::cell::RefCell;
+use std::env;
+use std::rc::Rc;
+
+pub fn define_globals(env: &mut Environment) {
+    define(env, NativeFunction::new("args", 0, args));
+    define(env, NativeFunction::new("getenv", 1, getenv));
+    define(env, NativeFunction::new("setenv", 2, setenv));
+    define(env, NativeFunction::with_optional("exit", 0, 1, exit));
+}
+
+// the command line arguments that came after the script path
+fn args(
+    intp: &mut Interpreter,
+    _args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let args = intp
+        .script_args
+        .iter()
+        .cloned()
+        .map(Value::String)
+        .collect();
+    Ok(Value::List(Rc::new(RefCell::new(args))))
+}
+
+// nil when the variable isn't set
+fn getenv(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let name = string_arg("getenv", &args[0])?;
+    Ok(env::var(name).map_or(Value::Nil, Value::String))
+}
+
+// setenv(name, nil) removes the variable
+fn setenv(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let name = string_arg("setenv", &args[0])?;
+    if name.is_empty() || name.contains('=') || name.contains('\0') {
+        return error!(format!(
+            "setenv() invalid variable name '{}'.",
+            name
+        ));
+    }
+    match &args[1] {
+        Value::Nil => env::remove_var(name),
+        value => {
+            let value = string_arg("setenv", value)?;
+            if value.contains('\0') {
+                return error!("setenv() value can't contain NUL.");
+            }
+            env::set_var(name, value)
+        }
+    }
+    Ok(Value::Nil)
+}
+
+// Doesn't exit right away: the exit code travels up the call stack like
+// a `return` so every borrow and environment is released on the way, and
+// main.rs turns it into the process exit status
+fn exit(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let code = match args.first() {
+        Some(code) => number_arg("exit", code)?,
+        None => 0.0,
+    };
+    if code.fract() != 0.0
+        || code < i32::MIN as f64
+        || code > i32::MAX as f64
+    {
+        error!(format!("exit() invalid exit code {}.", code))
+    } else {
+        Err(LoxError::Exit(code as i32))
+    }
+}
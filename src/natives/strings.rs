@@ -0,0 +1,257 @@
+// String natives. Every index and length is counted in Unicode scalar
+// values (Rust `char`s), never in bytes.
+use super::{define, index_arg, string_arg};
+use crate::environment::Environment;
+use crate::err::LoxError;
+use crate::functions::NativeFunction;
+use crate::interpreter::{Interpreter, Value};
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub fn define_globals(env: &mut Environment) {
+    define(env, NativeFunction::with_optional("substr", 2, 3, substr));
+    define(env, NativeFunction::with_optional("slice", 2, 3, slice));
+    define(env, NativeFunction::new("indexOf", 2, index_of));
+    define(env, NativeFunction::new("contains", 2, contains));
+    define(env, NativeFunction::new("startsWith", 2, starts_with));
+    define(env, NativeFunction::new("endsWith", 2, ends_with));
+    define(env, NativeFunction::new("split", 2, split));
+    define(env, NativeFunction::new("join", 2, join));
+    define(env, NativeFunction::new("trim", 1, trim));
+    define(env, NativeFunction::new("upper", 1, upper));
+    define(env, NativeFunction::new("lower", 1, lower));
+    define(env, NativeFunction::new("replace", 3, replace));
+    define(env, NativeFunction::new("repeat", 2, repeat));
+    define(env, NativeFunction::new("ord", 1, ord));
+    define(env, NativeFunction::new("chr", 1, chr));
+}
+
+// the characters of `value` between `start` and `end`, which may both
+// be at most the length of the string
+fn char_range(
+    fun: &str,
+    value: &str,
+    start: usize,
+    end: usize,
+) -> Result<Value, LoxError> {
+    let len = value.chars().count();
+    if start > len || end > len {
+        error!(format!(
+            "{}() index {} is out of bounds for a string of length {}.",
+            fun,
+            start.max(end),
+            len
+        ))
+    } else if start > end {
+        error!(format!(
+            "{}() start index {} is past end index {}.",
+            fun, start, end
+        ))
+    } else {
+        let sub = value.chars().skip(start).take(end - start).collect();
+        Ok(Value::String(sub))
+    }
+}
+
+// substr(s, start) or substr(s, start, length)
+fn substr(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let value = string_arg("substr", &args[0])?;
+    let start = index_arg("substr", &args[1])?;
+    let end = match args.get(2) {
+        Some(length) => start.saturating_add(index_arg("substr", length)?),
+        None => value.chars().count().max(start),
+    };
+    char_range("substr", value, start, end)
+}
+
+// slice(s, start) or slice(s, start, end), end is exclusive
+fn slice(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let value = string_arg("slice", &args[0])?;
+    let start = index_arg("slice", &args[1])?;
+    let end = match args.get(2) {
+        Some(end) => index_arg("slice", end)?,
+        None => value.chars().count().max(start),
+    };
+    char_range("slice", value, start, end)
+}
+
+// the index of the first occurrence of `sub`, or -1
+fn index_of(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let value = string_arg("indexOf", &args[0])?;
+    let sub = string_arg("indexOf", &args[1])?;
+    match value.find(sub) {
+        Some(byte_index) => {
+            Ok(Value::Number(value[..byte_index].chars().count() as f64))
+        }
+        None => Ok(Value::Number(-1.0)),
+    }
+}
+
+fn contains(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let value = string_arg("contains", &args[0])?;
+    let sub = string_arg("contains", &args[1])?;
+    Ok(Value::Bool(value.contains(sub)))
+}
+
+fn starts_with(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let value = string_arg("startsWith", &args[0])?;
+    let prefix = string_arg("startsWith", &args[1])?;
+    Ok(Value::Bool(value.starts_with(prefix)))
+}
+
+fn ends_with(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let value = string_arg("endsWith", &args[0])?;
+    let suffix = string_arg("endsWith", &args[1])?;
+    Ok(Value::Bool(value.ends_with(suffix)))
+}
+
+// splitting on "" gives the individual characters
+fn split(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let value = string_arg("split", &args[0])?;
+    let separator = string_arg("split", &args[1])?;
+    let parts = if separator.is_empty() {
+        value
+            .chars()
+            .map(|c| Value::String(c.to_string()))
+            .collect()
+    } else {
+        value
+            .split(separator)
+            .map(|part| Value::String(part.to_owned()))
+            .collect()
+    };
+    Ok(Value::List(Rc::new(RefCell::new(parts))))
+}
+
+// join(list, separator), the elements are printed the way `print` does
+fn join(
+    intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let separator = string_arg("join", &args[1])?;
+    match &args[0] {
+        Value::List(list) => {
+            let parts = list
+                .borrow()
+                .iter()
+                .map(|value| intp.stringify(value.clone()))
+                .collect::<Vec<_>>();
+            Ok(Value::String(parts.join(separator)))
+        }
+        value => error!(format!(
+            "join() expects a list but got {}.",
+            value.type_name()
+        )),
+    }
+}
+
+fn trim(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let value = string_arg("trim", &args[0])?;
+    Ok(Value::String(value.trim().to_owned()))
+}
+
+fn upper(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let value = string_arg("upper", &args[0])?;
+    Ok(Value::String(value.to_uppercase()))
+}
+
+fn lower(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let value = string_arg("lower", &args[0])?;
+    Ok(Value::String(value.to_lowercase()))
+}
+
+// replace(s, from, to) replaces every occurrence of `from`
+fn replace(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let value = string_arg("replace", &args[0])?;
+    let from = string_arg("replace", &args[1])?;
+    let to = string_arg("replace", &args[2])?;
+    if from.is_empty() {
+        error!("replace() can't replace an empty string.")
+    } else {
+        Ok(Value::String(value.replace(from, to)))
+    }
+}
+
+// the longest string repeat() makes, in bytes
+const MAX_REPEAT_LEN: usize = 1 << 30;
+
+fn repeat(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let value = string_arg("repeat", &args[0])?;
+    let count = index_arg("repeat", &args[1])?;
+    match value.len().checked_mul(count) {
+        Some(len) if len <= MAX_REPEAT_LEN => {
+            Ok(Value::String(value.repeat(count)))
+        }
+        _ => error!(format!(
+            "repeat() can't make a string of more than {} bytes.",
+            MAX_REPEAT_LEN
+        )),
+    }
+}
+
+// the code point of a single character string
+fn ord(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let value = string_arg("ord", &args[0])?;
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(Value::Number(c as u32 as f64)),
+        _ => error!(format!(
+            "ord() expects a single character but got a string of length {}.",
+            value.chars().count()
+        )),
+    }
+}
+
+fn chr(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let code = index_arg("chr", &args[0])?;
+    match char::from_u32(code.min(u32::MAX as usize) as u32) {
+        Some(c) => Ok(Value::String(c.to_string())),
+        None => error!(format!(
+            "chr() argument {} is not a valid Unicode scalar value.",
+            code
+        )),
+    }
+}
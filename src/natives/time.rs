@@ -0,0 +1,352 @@
+// Time natives. Timestamps are milliseconds since the Unix epoch and
+// calendar fields are in UTC. The time comes from the interpreter's
+// `clock`, see clock.rs.
+use super::{define, number_arg, string_arg};
+use crate::environment::Environment;
+use crate::err::LoxError;
+use crate::functions::NativeFunction;
+use crate::interpreter::{Interpreter, Value};
+
+use std::time::Duration;
+
+const MS_PER_DAY: i64 = 86_400_000;
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+pub fn define_globals(env: &mut Environment) {
+    define(env, NativeFunction::new("clock", 0, clock));
+    define(env, NativeFunction::new("now", 0, now));
+    define(env, NativeFunction::new("elapsed", 0, elapsed));
+    define(env, NativeFunction::new("sleep", 1, sleep));
+    define(env, NativeFunction::new("formatTime", 2, format_time));
+    define(env, NativeFunction::new("parseTime", 2, parse_time));
+}
+
+// seconds since the epoch, like `clock()` in other Lox implementations
+fn clock(
+    intp: &mut Interpreter,
+    _args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    Ok(Value::Number(intp.clock.now() / 1000.0))
+}
+
+fn now(
+    intp: &mut Interpreter,
+    _args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    Ok(Value::Number(intp.clock.now().floor()))
+}
+
+// milliseconds since the interpreter started, never goes backwards
+fn elapsed(
+    intp: &mut Interpreter,
+    _args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    Ok(Value::Number(intp.clock.elapsed()))
+}
+
+fn sleep(
+    intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let ms = number_arg("sleep", &args[0])?;
+    if ms < 0.0 || !ms.is_finite() {
+        return error!(format!(
+            "sleep() expects a non-negative duration but got {}.",
+            ms
+        ));
+    }
+    if Duration::try_from_secs_f64(ms / 1000.0).is_err() {
+        return error!("sleep() duration is too long.");
+    }
+    intp.clock.sleep(ms);
+    Ok(Value::Nil)
+}
+
+// A timestamp split into its UTC calendar fields
+struct DateTime {
+    year: i64,
+    month: u32, // 1 to 12
+    day: u32,   // 1 to 31
+    hour: u32,
+    minute: u32,
+    second: u32,
+    milli: u32,
+}
+
+// Conversions between days since the epoch and civil dates, from
+// Howard Hinnant's "chrono-Compatible Low-Level Date Algorithms"
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let month = month as i64;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    let next = if month == 12 {
+        days_from_civil(year + 1, 1, 1)
+    } else {
+        days_from_civil(year, month + 1, 1)
+    };
+    (next - days_from_civil(year, month, 1)) as u32
+}
+
+impl DateTime {
+    fn from_timestamp(ms: i64) -> DateTime {
+        let days = ms.div_euclid(MS_PER_DAY);
+        let rest = ms.rem_euclid(MS_PER_DAY);
+        let (year, month, day) = civil_from_days(days);
+        DateTime {
+            year,
+            month,
+            day,
+            hour: (rest / 3_600_000) as u32,
+            minute: (rest / 60_000 % 60) as u32,
+            second: (rest / 1000 % 60) as u32,
+            milli: (rest % 1000) as u32,
+        }
+    }
+
+    fn timestamp(&self) -> i64 {
+        days_from_civil(self.year, self.month, self.day) * MS_PER_DAY
+            + self.hour as i64 * 3_600_000
+            + self.minute as i64 * 60_000
+            + self.second as i64 * 1000
+            + self.milli as i64
+    }
+
+    fn weekday(&self) -> usize {
+        // 1970-01-01 was a Thursday
+        (days_from_civil(self.year, self.month, self.day) + 4)
+            .rem_euclid(7) as usize
+    }
+}
+
+// formatTime(ts, fmt) supports the strftime directives %Y %y %m %d %e
+// %H %M %S %L (milliseconds) %j %a %A %b %B %s %Z and %%
+fn format_time(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let ts = number_arg("formatTime", &args[0])?;
+    let fmt = string_arg("formatTime", &args[1])?;
+    if !ts.is_finite() {
+        return error!(format!("formatTime() invalid timestamp {}.", ts));
+    }
+    let ms = ts.floor() as i64;
+    let date = DateTime::from_timestamp(ms);
+    let mut out = String::new();
+    let mut chars = fmt.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => out.push_str(&format!("{:04}", date.year)),
+            Some('y') => {
+                out.push_str(&format!("{:02}", date.year.rem_euclid(100)))
+            }
+            Some('m') => out.push_str(&format!("{:02}", date.month)),
+            Some('d') => out.push_str(&format!("{:02}", date.day)),
+            Some('e') => out.push_str(&format!("{:2}", date.day)),
+            Some('H') => out.push_str(&format!("{:02}", date.hour)),
+            Some('M') => out.push_str(&format!("{:02}", date.minute)),
+            Some('S') => out.push_str(&format!("{:02}", date.second)),
+            Some('L') => out.push_str(&format!("{:03}", date.milli)),
+            Some('j') => {
+                let day_of_year =
+                    days_from_civil(date.year, date.month, date.day)
+                        - days_from_civil(date.year, 1, 1)
+                        + 1;
+                out.push_str(&format!("{:03}", day_of_year))
+            }
+            Some('a') => out.push_str(&WEEKDAYS[date.weekday()][..3]),
+            Some('A') => out.push_str(WEEKDAYS[date.weekday()]),
+            Some('b') => {
+                out.push_str(&MONTHS[date.month as usize - 1][..3])
+            }
+            Some('B') => out.push_str(MONTHS[date.month as usize - 1]),
+            Some('s') => out.push_str(&ms.div_euclid(1000).to_string()),
+            Some('Z') => out.push_str("UTC"),
+            Some('%') => out.push('%'),
+            Some(other) => {
+                return error!(format!(
+                    "formatTime() unknown directive '%{}'.",
+                    other
+                ))
+            }
+            None => return error!("formatTime() format ends with '%'."),
+        }
+    }
+    Ok(Value::String(out))
+}
+
+// parseTime(str, fmt) reads a UTC time written in the given format and
+// returns its timestamp. It understands %Y %m %d %e %H %M %S %L %b %B
+// and %%, fields that are missing from the format default to the epoch.
+fn parse_time(
+    _intp: &mut Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, LoxError> {
+    let text = string_arg("parseTime", &args[0])?;
+    let fmt = string_arg("parseTime", &args[1])?;
+    let input = text.chars().collect::<Vec<_>>();
+    let mut pos = 0;
+    let mut date = DateTime {
+        year: 1970,
+        month: 1,
+        day: 1,
+        hour: 0,
+        minute: 0,
+        second: 0,
+        milli: 0,
+    };
+    let mismatch = |pos: usize, expected: &str| {
+        LoxError::Error(format!(
+            "parseTime() expected {} at position {} of '{}'.",
+            expected, pos, text
+        ))
+    };
+
+    let mut directives = fmt.chars();
+    while let Some(c) = directives.next() {
+        if c != '%' {
+            if input.get(pos) != Some(&c) {
+                return Err(mismatch(pos, &format!("'{}'", c)));
+            }
+            pos += 1;
+            continue;
+        }
+        let directive = match directives.next() {
+            Some(directive) => directive,
+            None => return error!("parseTime() format ends with '%'."),
+        };
+        // reads a number of at most `width` digits
+        let mut number = |width: usize, what: &str| {
+            let start = pos;
+            if directive == 'e' && input.get(pos) == Some(&' ') {
+                pos += 1;
+            }
+            let digits_start = pos;
+            while pos < input.len()
+                && pos - digits_start < width
+                && input[pos].is_ascii_digit()
+            {
+                pos += 1;
+            }
+            if pos == digits_start {
+                return Err(mismatch(start, what));
+            }
+            let digits: String = input[digits_start..pos].iter().collect();
+            Ok(digits.parse::<i64>().unwrap())
+        };
+        match directive {
+            'Y' => date.year = number(4, "a year")?,
+            'm' => date.month = number(2, "a month")? as u32,
+            'd' | 'e' => date.day = number(2, "a day")? as u32,
+            'H' => date.hour = number(2, "an hour")? as u32,
+            'M' => date.minute = number(2, "minutes")? as u32,
+            'S' => date.second = number(2, "seconds")? as u32,
+            'L' => date.milli = number(3, "milliseconds")? as u32,
+            'b' | 'B' => {
+                let rest: String = input[pos..].iter().collect();
+                let rest = rest.to_lowercase();
+                let found =
+                    MONTHS.iter().enumerate().find_map(|(i, name)| {
+                        let name = name.to_lowercase();
+                        let short = &name[..3];
+                        if directive == 'B' && rest.starts_with(&name) {
+                            Some((i, name.len()))
+                        } else if directive == 'b'
+                            && rest.starts_with(short)
+                        {
+                            Some((i, 3))
+                        } else {
+                            None
+                        }
+                    });
+                match found {
+                    Some((i, len)) => {
+                        date.month = i as u32 + 1;
+                        pos += len;
+                    }
+                    None => return Err(mismatch(pos, "a month name")),
+                }
+            }
+            '%' => {
+                if input.get(pos) != Some(&'%') {
+                    return Err(mismatch(pos, "'%'"));
+                }
+                pos += 1;
+            }
+            other => {
+                return error!(format!(
+                    "parseTime() unknown directive '%{}'.",
+                    other
+                ))
+            }
+        }
+    }
+    if pos < input.len() {
+        return error!(format!(
+            "parseTime() unexpected text at position {} of '{}'.",
+            pos, text
+        ));
+    }
+    if date.month < 1
+        || date.month > 12
+        || date.day < 1
+        || date.day > days_in_month(date.year, date.month)
+        || date.hour > 23
+        || date.minute > 59
+        || date.second > 59
+    {
+        return error!(format!(
+            "parseTime() '{}' is not a valid time.",
+            text
+        ));
+    }
+    Ok(Value::Number(date.timestamp() as f64))
+}
@@ -1,17 +1,23 @@
 use crate::err::LoxError;
 use crate::expr::{
-    AssignExpr, BinaryExpr, CallExpr, Expr, GroupExpr, NumberExpr,
-    UnaryExpr, VariableExpr,
+    AssignExpr, BinaryExpr, CallExpr, CoalesceExpr, Expr, GetExpr,
+    GroupExpr, IndexExpr, IsExpr, ListExpr, MapExpr, NumberExpr,
+    RangeExpr, SetExpr, SetIndexExpr, TupleExpr, UnaryExpr, VariableExpr,
 };
 use crate::stmt::{
-    BlockStmt, ExpressionStmt, FunStmt, IfStmt, PrintStmt, ReturnStmt,
-    Stmt, VarStmt, WhileStmt,
+    AssertStmt, BlockStmt, EnumStmt, EnumVariant, ExpressionStmt,
+    ForInStmt, ForStmt, FunStmt, IfStmt, PrintStmt, ReturnStmt, Stmt,
+    StructStmt, TestStmt, VarStmt, WhileStmt, YieldStmt,
 };
 use crate::token::{Token, TokenType};
 
+use std::rc::Rc;
+
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    in_generator: bool, // `yield` is only valid inside `fun*` bodies
+    in_function: bool,  // and `return` inside any function
 }
 
 macro_rules! matches {
@@ -29,7 +35,12 @@ macro_rules! matches {
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Parser {
-        Parser { tokens, current: 0 }
+        Parser {
+            tokens,
+            current: 0,
+            in_generator: false,
+            in_function: false,
+        }
     }
 
     fn is_end(&self) -> bool {
@@ -70,25 +81,153 @@ impl Parser {
         self.peek().token_type == t
     }
 
+    // looks one token past the current one
+    fn check_next(&self, t: TokenType) -> bool {
+        match self.tokens.get(self.current + 1) {
+            Some(token) => token.token_type == t,
+            None => false,
+        }
+    }
+
     pub fn parse(&mut self) -> Result<Vec<Stmt>, LoxError> {
         let mut statements: Vec<Stmt> = Vec::new();
         while !self.is_end() {
-            statements.push(self.declaration()?);
+            if self.at_test_block() {
+                statements.push(self.test_block()?);
+            } else {
+                statements.push(self.declaration()?);
+            }
         }
         Ok(statements)
     }
 
+    // `test` is only a keyword when a name follows it
+    fn at_test_block(&self) -> bool {
+        self.check(TokenType::Identifier)
+            && self.peek().lexeme == "test"
+            && self.check_next(TokenType::StrLit)
+    }
+
     fn declaration(&mut self) -> Result<Stmt, LoxError> {
         if matches!(self, TokenType::Var) {
             self.var_declaration()
         } else if matches!(self, TokenType::Fun) {
             self.function()
+        } else if matches!(self, TokenType::Enum) {
+            self.enum_declaration()
+        } else if matches!(self, TokenType::Struct) {
+            self.struct_declaration()
+        } else if self.at_test_block() {
+            // `rena test` only looks for tests at the top level of a file
+            error!("Test blocks can only be declared at the top level.")
         } else {
             self.statement()
         }
     }
 
+    // enum Shape { Circle(r), Rect(w, h), Empty }
+    fn enum_declaration(&mut self) -> Result<Stmt, LoxError> {
+        let name =
+            self.consume(TokenType::Identifier, "Expect enum name.")?;
+        self.consume(TokenType::LeftBrace, "Expect '{' after enum name.")?;
+        let mut variants: Vec<EnumVariant> = vec![];
+        while !self.check(TokenType::RightBrace) {
+            let variant = self
+                .consume(TokenType::Identifier, "Expect variant name.")?;
+            if variants.iter().any(|v| v.name.lexeme == variant.lexeme) {
+                return error!(format!(
+                    "Duplicate variant '{}' in enum '{}'.",
+                    variant.lexeme, name.lexeme
+                ));
+            }
+            let mut fields: Vec<Token> = vec![];
+            if matches!(self, TokenType::LeftParen) {
+                while !self.check(TokenType::RightParen) {
+                    let field = self.consume(
+                        TokenType::Identifier,
+                        "Expect field name.",
+                    )?;
+                    if fields.iter().any(|f| f.lexeme == field.lexeme) {
+                        return error!(format!(
+                            "Duplicate field '{}' in variant '{}.{}'.",
+                            field.lexeme, name.lexeme, variant.lexeme
+                        ));
+                    }
+                    fields.push(field);
+                    if !matches!(self, TokenType::Comma) {
+                        break;
+                    }
+                }
+                self.consume(
+                    TokenType::RightParen,
+                    "Expect ')' after variant fields.",
+                )?;
+            }
+            variants.push(EnumVariant {
+                name: variant,
+                fields,
+            });
+            if !matches!(self, TokenType::Comma) {
+                break;
+            }
+        }
+        self.consume(
+            TokenType::RightBrace,
+            "Expect '}' after enum variants.",
+        )?;
+        Ok(Stmt::Enum(EnumStmt { name, variants }))
+    }
+
+    // struct Point { x, y }
+    fn struct_declaration(&mut self) -> Result<Stmt, LoxError> {
+        let name =
+            self.consume(TokenType::Identifier, "Expect struct name.")?;
+        self.consume(
+            TokenType::LeftBrace,
+            "Expect '{' after struct name.",
+        )?;
+        let mut fields: Vec<Token> = vec![];
+        while !self.check(TokenType::RightBrace) {
+            let field =
+                self.consume(TokenType::Identifier, "Expect field name.")?;
+            if fields.iter().any(|f| f.lexeme == field.lexeme) {
+                return error!(format!(
+                    "Duplicate field '{}' in struct '{}'.",
+                    field.lexeme, name.lexeme
+                ));
+            }
+            fields.push(field);
+            if !matches!(self, TokenType::Comma) {
+                break;
+            }
+        }
+        self.consume(
+            TokenType::RightBrace,
+            "Expect '}' after struct fields.",
+        )?;
+        Ok(Stmt::Struct(StructStmt { name, fields }))
+    }
+
+    // test "addition works" { assert 1 + 1 == 2; }
+    fn test_block(&mut self) -> Result<Stmt, LoxError> {
+        let keyword = self.advance();
+        let name = self.advance().lexeme;
+        self.consume(TokenType::LeftBrace, "Expect '{' after test name.")?;
+        let enclosing = self.in_generator;
+        self.in_generator = false;
+        let body = self.block_stmt();
+        self.in_generator = enclosing;
+        Ok(Stmt::Test(TestStmt {
+            keyword,
+            name,
+            body: body?.into(),
+        }))
+    }
+
+    // fun add(a, b) { ... }
+    // fun* numbers() { yield 1; ... }
     fn function(&mut self) -> Result<Stmt, LoxError> {
+        let is_generator = matches!(self, TokenType::Star);
         let name =
             self.consume(TokenType::Identifier, "Expect function name.")?;
         self.consume(
@@ -97,50 +236,115 @@ impl Parser {
         )?;
         // parse all parameters
         let mut params: Vec<Token> = vec![];
+        let mut param_types = vec![];
         if !self.check(TokenType::RightParen) {
-            params.push(self.consume(
-                TokenType::Identifier,
-                "Expect parameter name.",
-            )?);
-            while matches!(self, TokenType::Comma) {
-                if params.len() < 255 {
-                    params.push(self.consume(
-                        TokenType::Identifier,
-                        "Expect parameter name.",
-                    )?);
-                } else {
+            loop {
+                if params.len() >= 255 {
                     return error!("Can't have more than 255 parameters.");
                 }
+                params.push(self.consume(
+                    TokenType::Identifier,
+                    "Expect parameter name.",
+                )?);
+                param_types.push(self.annotation()?);
+                if !matches!(self, TokenType::Comma) {
+                    break;
+                }
             }
         }
         self.consume(
             TokenType::RightParen,
             "Expect ')' after parameters.",
         )?;
+        let return_type = if matches!(self, TokenType::Arrow) {
+            Some(self.type_name()?)
+        } else {
+            None
+        };
 
         self.consume(
             TokenType::LeftBrace,
             "Expect '{' before function body",
         )?;
-        let body = self.block_stmt()?;
-        Ok(Stmt::Function(FunStmt { name, params, body }))
+        let enclosing = (self.in_generator, self.in_function);
+        self.in_generator = is_generator;
+        self.in_function = true;
+        let body = self.block_stmt();
+        (self.in_generator, self.in_function) = enclosing;
+        Ok(Stmt::Function(FunStmt {
+            name,
+            params,
+            param_types,
+            return_type,
+            body: body?.into(),
+            is_generator,
+        }))
     }
 
     // var a = 5;
     // var b;
+    // var c: number = 5;
+    // var (q, r) = divmod(7, 2);
     fn var_declaration(&mut self) -> Result<Stmt, LoxError> {
+        if matches!(self, TokenType::LeftParen) {
+            return self.unpack_declaration();
+        }
         let name =
             self.consume(TokenType::Identifier, "Expect variable name.")?;
+        let type_name = self.annotation()?;
+        let mut init = None;
         if matches!(self, TokenType::Equal) {
-            let init = self.expression()?;
-            self.consume(TokenType::Semicolon, "Expect semicolon.")?;
-            Ok(Stmt::Var(VarStmt {
-                name,
-                init: Some(init),
-            }))
+            init = Some(self.expression()?);
+        }
+        self.consume(TokenType::Semicolon, "Expect semicolon.")?;
+        Ok(Stmt::Var(VarStmt {
+            names: vec![name],
+            unpack: false,
+            type_name,
+            init,
+        }))
+    }
+
+    fn unpack_declaration(&mut self) -> Result<Stmt, LoxError> {
+        let mut names: Vec<Token> = vec![];
+        loop {
+            let name = self
+                .consume(TokenType::Identifier, "Expect variable name.")?;
+            if names.iter().any(|n| n.lexeme == name.lexeme) {
+                return error!(format!(
+                    "Variable '{}' is unpacked twice.",
+                    name.lexeme
+                ));
+            }
+            names.push(name);
+            if !matches!(self, TokenType::Comma) {
+                break;
+            }
+        }
+        self.consume(
+            TokenType::RightParen,
+            "Expect ')' after variable names.",
+        )?;
+        self.consume(
+            TokenType::Equal,
+            "Expect '=' after variables to unpack.",
+        )?;
+        let init = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect semicolon.")?;
+        Ok(Stmt::Var(VarStmt {
+            names,
+            unpack: true,
+            type_name: None,
+            init: Some(init),
+        }))
+    }
+
+    // an optional `: type` after a variable or parameter name
+    fn annotation(&mut self) -> Result<Option<Token>, LoxError> {
+        if matches!(self, TokenType::Colon) {
+            Ok(Some(self.type_name()?))
         } else {
-            self.consume(TokenType::Semicolon, "Expect semicolon.")?;
-            Ok(Stmt::Var(VarStmt { name, init: None }))
+            Ok(None)
         }
     }
 
@@ -150,7 +354,9 @@ impl Parser {
             self.print_stmt()
         } else if matches!(self, TokenType::LeftBrace) {
             let statements = self.block_stmt()?;
-            Ok(Stmt::Block(BlockStmt { statements }))
+            Ok(Stmt::Block(BlockStmt {
+                statements: statements.into(),
+            }))
         } else if matches!(self, TokenType::If) {
             self.if_stmt()
         } else if matches!(self, TokenType::While) {
@@ -159,6 +365,10 @@ impl Parser {
             self.for_stmt()
         } else if matches!(self, TokenType::Return) {
             self.return_stmt()
+        } else if matches!(self, TokenType::Yield) {
+            self.yield_stmt()
+        } else if matches!(self, TokenType::Assert) {
+            self.assert_stmt()
         } else {
             self.expression_stmt()
         }
@@ -166,6 +376,9 @@ impl Parser {
 
     fn return_stmt(&mut self) -> Result<Stmt, LoxError> {
         let keyword = self.previous();
+        if !self.in_function {
+            return error!("Can't return from top-level code.");
+        }
         let mut value = None;
         if !self.check(TokenType::Semicolon) {
             value = Some(self.expression()?);
@@ -177,6 +390,24 @@ impl Parser {
         Ok(Stmt::Return(ReturnStmt { keyword, value }))
     }
 
+    fn yield_stmt(&mut self) -> Result<Stmt, LoxError> {
+        let keyword = self.previous();
+        if !self.in_generator {
+            return error!(
+                "Can't use 'yield' outside a generator function."
+            );
+        }
+        let mut value = None;
+        if !self.check(TokenType::Semicolon) {
+            value = Some(self.expression()?);
+        }
+        self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after yield value.",
+        )?;
+        Ok(Stmt::Yield(YieldStmt { keyword, value }))
+    }
+
     fn for_stmt(&mut self) -> Result<Stmt, LoxError> {
         self.consume(TokenType::LeftParen, "Expect '(' after 'for'.")?;
 
@@ -184,6 +415,9 @@ impl Parser {
         if matches!(self, TokenType::Semicolon) {
             init = None;
         } else if matches!(self, TokenType::Var) {
+            if self.check_next(TokenType::In) {
+                return self.for_in_stmt();
+            }
             init = Some(self.var_declaration()?);
         } else {
             init = Some(self.expression_stmt()?);
@@ -206,25 +440,31 @@ impl Parser {
             TokenType::RightParen,
             "Expect ')' after for clauses.",
         )?;
-        let mut body = self.statement()?;
-        if let Some(increment_expression) = increment {
-            let increment_stmt = Stmt::Expression(ExpressionStmt {
-                expr: increment_expression,
-            });
-            body = Stmt::Block(BlockStmt {
-                statements: vec![body, increment_stmt],
-            });
-        }
-        body = Stmt::While(WhileStmt {
-            condition: condition.unwrap_or(Expr::Boolean(true)),
+        let body = self.statement()?;
+        Ok(Stmt::For(Rc::new(ForStmt {
+            init: init.map(Box::new),
+            condition,
+            increment,
             body: Box::new(body),
-        });
-        if let Some(init_statement) = init {
-            body = Stmt::Block(BlockStmt {
-                statements: vec![init_statement, body],
-            });
-        }
-        Ok(body)
+        })))
+    }
+
+    // for (var x in iterable) body
+    fn for_in_stmt(&mut self) -> Result<Stmt, LoxError> {
+        let name =
+            self.consume(TokenType::Identifier, "Expect variable name.")?;
+        self.consume(TokenType::In, "Expect 'in' after loop variable.")?;
+        let iterable = self.expression()?;
+        self.consume(
+            TokenType::RightParen,
+            "Expect ')' after for clauses.",
+        )?;
+        let body = self.statement()?;
+        Ok(Stmt::ForIn(Rc::new(ForInStmt {
+            name,
+            iterable,
+            body: Box::new(body),
+        })))
     }
 
     fn while_stmt(&mut self) -> Result<Stmt, LoxError> {
@@ -235,10 +475,10 @@ impl Parser {
             "Expect ')' after condition.",
         )?;
         let body = self.statement()?;
-        Ok(Stmt::While(WhileStmt {
+        Ok(Stmt::While(Rc::new(WhileStmt {
             condition,
             body: Box::new(body),
-        }))
+        })))
     }
 
     // if (1 > 4) {
@@ -276,10 +516,34 @@ impl Parser {
         Ok(statements)
     }
 
+    // assert a == b;
+    // assert len(xs) > 0, "xs is empty";
+    fn assert_stmt(&mut self) -> Result<Stmt, LoxError> {
+        let keyword = self.previous();
+        let start = self.current;
+        let condition = self.expression()?;
+        let source = source_text(&self.tokens[start..self.current]);
+        let mut message = None;
+        if matches!(self, TokenType::Comma) {
+            message = Some(self.expression()?);
+        }
+        self.consume(TokenType::Semicolon, "Expect ';' after assertion.")?;
+        Ok(Stmt::Assert(AssertStmt {
+            keyword,
+            condition,
+            source,
+            message,
+        }))
+    }
+
     fn print_stmt(&mut self) -> Result<Stmt, LoxError> {
         let expr = self.expression()?;
+        let mut args = vec![];
+        while matches!(self, TokenType::Comma) {
+            args.push(self.expression()?);
+        }
         self.consume(TokenType::Semicolon, "Expect semicolon.")?;
-        Ok(Stmt::Print(PrintStmt { expr }))
+        Ok(Stmt::Print(PrintStmt { expr, args }))
     }
 
     fn expression_stmt(&mut self) -> Result<Stmt, LoxError> {
@@ -294,7 +558,7 @@ impl Parser {
 
     fn assignment(&mut self) -> Result<Expr, LoxError> {
         // println!("assignment()");
-        let expr = self.equality()?;
+        let expr = self.coalesce()?;
         if matches!(self, TokenType::Equal) {
             let _equals = self.previous();
             let value = self.assignment()?;
@@ -303,6 +567,17 @@ impl Parser {
                     name: expr.name,
                     value: Box::new(value),
                 })),
+                Expr::Get(expr) => Ok(Expr::Set(SetExpr {
+                    object: expr.object,
+                    name: expr.name,
+                    value: Box::new(value),
+                })),
+                Expr::Index(expr) => Ok(Expr::SetIndex(SetIndexExpr {
+                    object: expr.object,
+                    bracket: expr.bracket,
+                    index: expr.index,
+                    value: Box::new(value),
+                })),
                 _ => error!("Invalid assignment target."),
             }
         } else {
@@ -310,6 +585,52 @@ impl Parser {
         }
     }
 
+    // `a ?? b` binds looser than `|>`, so `x ?? y |> f` is `x ?? f(y)`
+    fn coalesce(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.pipeline()?;
+        while matches!(self, TokenType::QuestionQuestion) {
+            let right = self.pipeline()?;
+            expr = Expr::Coalesce(CoalesceExpr {
+                left: Box::new(expr),
+                right: Box::new(right),
+            });
+        }
+        Ok(expr)
+    }
+
+    // `x |> f(a)` is rewritten into `f(x, a)` and `x |> f` into `f(x)`
+    fn pipeline(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.equality()?;
+        while matches!(self, TokenType::Pipe) {
+            let pipe = self.previous();
+            expr = match self.equality()? {
+                Expr::Call(mut call) => {
+                    call.args.insert(0, expr);
+                    Expr::Call(call)
+                }
+                Expr::OptionalCall(mut call) => {
+                    call.args.insert(0, expr);
+                    Expr::OptionalCall(call)
+                }
+                callee @ Expr::Variable(_)
+                | callee @ Expr::Get(_)
+                | callee @ Expr::Index(_)
+                | callee @ Expr::Group(_) => Expr::Call(CallExpr {
+                    callee: Box::new(callee),
+                    paren: pipe,
+                    args: vec![expr],
+                    named: vec![],
+                }),
+                _ => {
+                    return error!(
+                        "Expect a function or a call after '|>'."
+                    )
+                }
+            };
+        }
+        Ok(expr)
+    }
+
     fn equality(&mut self) -> Result<Expr, LoxError> {
         let mut expr = self.comparison()?;
         while matches!(self, TokenType::BangEqual, TokenType::EqualEqual) {
@@ -325,16 +646,25 @@ impl Parser {
     }
 
     fn comparison(&mut self) -> Result<Expr, LoxError> {
-        let mut expr = self.term()?;
+        let mut expr = self.range()?;
         while matches!(
             self,
             TokenType::Greater,
             TokenType::GreaterEqual,
             TokenType::Less,
-            TokenType::LessEqual
+            TokenType::LessEqual,
+            TokenType::In,
+            TokenType::Is
         ) {
             let oper = self.previous();
-            let right = self.term()?;
+            if oper.token_type == TokenType::Is {
+                expr = Expr::Is(IsExpr {
+                    value: Box::new(expr),
+                    type_name: self.type_name()?,
+                });
+                continue;
+            }
+            let right = self.range()?;
             expr = Expr::Binary(BinaryExpr {
                 left: Box::new(expr),
                 oper,
@@ -344,6 +674,48 @@ impl Parser {
         Ok(expr)
     }
 
+    // the name after `is`, `:` or `->`; `nil`, `enum` and `struct` are
+    // keywords, everything else is an identifier
+    fn type_name(&mut self) -> Result<Token, LoxError> {
+        if matches!(
+            self,
+            TokenType::Nil,
+            TokenType::Enum,
+            TokenType::Struct,
+            TokenType::Identifier
+        ) {
+            Ok(self.previous())
+        } else {
+            error!("Expect type name.")
+        }
+    }
+
+    // 0..10, 0..=10, 10..0 step -2
+    fn range(&mut self) -> Result<Expr, LoxError> {
+        let start = self.term()?;
+        if matches!(self, TokenType::DotDot, TokenType::DotDotEqual) {
+            let inclusive =
+                self.previous().token_type == TokenType::DotDotEqual;
+            let end = self.term()?;
+            let mut step = None;
+            // `step` is only a keyword right after a range
+            if self.check(TokenType::Identifier)
+                && self.peek().lexeme == "step"
+            {
+                self.advance();
+                step = Some(Box::new(self.term()?));
+            }
+            Ok(Expr::Range(RangeExpr {
+                start: Box::new(start),
+                end: Box::new(end),
+                step,
+                inclusive,
+            }))
+        } else {
+            Ok(start)
+        }
+    }
+
     fn term(&mut self) -> Result<Expr, LoxError> {
         let mut expr = self.factor()?;
         while matches!(self, TokenType::Minus, TokenType::Plus) {
@@ -389,7 +761,34 @@ impl Parser {
         let mut expr = self.primary()?;
         loop {
             if matches!(self, TokenType::LeftParen) {
-                expr = self.finish_call(expr)?;
+                expr = Expr::Call(self.finish_call(expr)?);
+            } else if matches!(self, TokenType::QuestionDot) {
+                self.consume(
+                    TokenType::LeftParen,
+                    "Expect '(' after '?.'.",
+                )?;
+                expr = Expr::OptionalCall(self.finish_call(expr)?);
+            } else if matches!(self, TokenType::Dot) {
+                let name = self.consume(
+                    TokenType::Identifier,
+                    "Expect property name after '.'.",
+                )?;
+                expr = Expr::Get(GetExpr {
+                    object: Box::new(expr),
+                    name,
+                });
+            } else if matches!(self, TokenType::LeftBracket) {
+                let bracket = self.previous();
+                let index = self.expression()?;
+                self.consume(
+                    TokenType::RightBracket,
+                    "Expect ']' after index.",
+                )?;
+                expr = Expr::Index(IndexExpr {
+                    object: Box::new(expr),
+                    bracket,
+                    index: Box::new(index),
+                });
             } else {
                 break;
             }
@@ -397,26 +796,69 @@ impl Parser {
         Ok(expr)
     }
 
-    fn finish_call(&mut self, callee: Expr) -> Result<Expr, LoxError> {
+    // f(a, b) or, for structs, Point(x: 1, y: 2)
+    fn finish_call(&mut self, callee: Expr) -> Result<CallExpr, LoxError> {
         let mut args: Vec<Expr> = vec![];
+        let mut named: Vec<(Token, Expr)> = vec![];
         if !self.check(TokenType::RightParen) {
-            args.push(self.expression()?); // parse first argument
-            while matches!(self, TokenType::Comma) {
-                if args.len() >= 255 {
+            loop {
+                if args.len() + named.len() >= 255 {
                     return error!("Can't have more than 255 arguments.");
-                };
-                args.push(self.expression()?);
+                }
+                if self.check(TokenType::Identifier)
+                    && self.check_next(TokenType::Colon)
+                {
+                    let name = self.advance();
+                    self.advance(); // the colon
+                    named.push((name, self.expression()?));
+                } else if named.is_empty() {
+                    args.push(self.expression()?);
+                } else {
+                    return error!(
+                        "Positional arguments must come before named ones."
+                    );
+                }
+                if !matches!(self, TokenType::Comma) {
+                    break;
+                }
             }
         }
         let paren = self.consume(
             TokenType::RightParen,
             "Expect ')' after arguments.",
         )?;
-        Ok(Expr::Call(CallExpr {
+        Ok(CallExpr {
             callee: Box::new(callee),
             paren,
             args,
-        }))
+            named,
+        })
+    }
+
+    // (a) is a group, a comma makes a tuple: (), (a,) and (a, b)
+    fn group_or_tuple(&mut self) -> Result<Expr, LoxError> {
+        if matches!(self, TokenType::RightParen) {
+            return Ok(Expr::Tuple(TupleExpr { elements: vec![] }));
+        }
+        let expr = self.expression()?;
+        if !matches!(self, TokenType::Comma) {
+            self.consume(
+                TokenType::RightParen,
+                "Expect ')' after expression",
+            )?;
+            return Ok(Expr::Group(GroupExpr {
+                expr: Box::new(expr),
+            }));
+        }
+        let mut elements = vec![expr];
+        while !self.check(TokenType::RightParen) {
+            elements.push(self.expression()?);
+            if !matches!(self, TokenType::Comma) {
+                break;
+            }
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after tuple.")?;
+        Ok(Expr::Tuple(TupleExpr { elements }))
     }
 
     fn primary(&mut self) -> Result<Expr, LoxError> {
@@ -435,14 +877,11 @@ impl Parser {
             let str_lit = self.previous().lexeme;
             Ok(Expr::String(str_lit))
         } else if matches!(self, TokenType::LeftParen) {
-            let expr = self.expression()?;
-            self.consume(
-                TokenType::RightParen,
-                "Expect ')' after expression",
-            )?;
-            Ok(Expr::Group(GroupExpr {
-                expr: Box::new(expr),
-            }))
+            self.group_or_tuple()
+        } else if matches!(self, TokenType::LeftBracket) {
+            self.list()
+        } else if matches!(self, TokenType::LeftBrace) {
+            self.map()
         } else if matches!(self, TokenType::Identifier) {
             Ok(Expr::Variable(VariableExpr {
                 name: self.previous(),
@@ -451,4 +890,109 @@ impl Parser {
             error!("Expect expressions.")
         }
     }
+
+    // [1, 2, 3]
+    fn list(&mut self) -> Result<Expr, LoxError> {
+        let mut elements = vec![];
+        if !self.check(TokenType::RightBracket) {
+            elements.push(self.expression()?);
+            while matches!(self, TokenType::Comma) {
+                elements.push(self.expression()?);
+            }
+        }
+        self.consume(
+            TokenType::RightBracket,
+            "Expect ']' after list elements.",
+        )?;
+        Ok(Expr::List(ListExpr { elements }))
+    }
+
+    // {"name": "rena", version: 1}, keys are strings or identifiers
+    fn map(&mut self) -> Result<Expr, LoxError> {
+        let mut entries = vec![];
+        if !self.check(TokenType::RightBrace) {
+            loop {
+                if !matches!(
+                    self,
+                    TokenType::StrLit,
+                    TokenType::Identifier
+                ) {
+                    return error!(
+                        "Expect string or identifier as map key."
+                    );
+                }
+                let key = self.previous().lexeme;
+                self.consume(
+                    TokenType::Colon,
+                    "Expect ':' after map key.",
+                )?;
+                entries.push((key, self.expression()?));
+                if !matches!(self, TokenType::Comma) {
+                    break;
+                }
+            }
+        }
+        self.consume(
+            TokenType::RightBrace,
+            "Expect '}' after map entries.",
+        )?;
+        Ok(Expr::Map(MapExpr { entries }))
+    }
+}
+
+// Rebuilds the source of an expression from its tokens, with the spacing
+// normalised: `f( a )[0]==-1` comes back as `f(a)[0] == -1`
+fn source_text(tokens: &[Token]) -> String {
+    let mut text = String::new();
+    let mut previous: Option<&Token> = None;
+    let mut previous_unary = false;
+    for token in tokens {
+        let ends_value = previous.is_some_and(|prev| {
+            [
+                TokenType::Identifier,
+                TokenType::Number,
+                TokenType::StrLit,
+                TokenType::True,
+                TokenType::False,
+                TokenType::Nil,
+                TokenType::RightParen,
+                TokenType::RightBracket,
+                TokenType::RightBrace,
+            ]
+            .contains(&prev.token_type)
+        });
+        let unary = !ends_value
+            && (token.token_type == TokenType::Minus
+                || token.token_type == TokenType::Bang);
+        let tight_after = previous.is_none_or(|prev| {
+            previous_unary
+                || [
+                    TokenType::LeftParen,
+                    TokenType::LeftBracket,
+                    TokenType::Dot,
+                ]
+                .contains(&prev.token_type)
+        });
+        let tight_before = match token.token_type {
+            TokenType::RightParen
+            | TokenType::RightBracket
+            | TokenType::Comma
+            | TokenType::Colon
+            | TokenType::Dot => true,
+            // calls and indexing
+            TokenType::LeftParen | TokenType::LeftBracket => ends_value,
+            _ => false,
+        };
+        if !(tight_after || tight_before) {
+            text.push(' ');
+        }
+        if token.token_type == TokenType::StrLit {
+            text.push_str(&format!("\"{}\"", token.lexeme));
+        } else {
+            text.push_str(&token.lexeme);
+        }
+        previous = Some(token);
+        previous_unary = unary;
+    }
+    text
 }
@@ -0,0 +1,86 @@
+use crate::err::LoxError;
+
+// A lazy sequence of numbers from `start` up to `end`, which is only
+// part of the sequence for inclusive ranges
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Range {
+    pub start: f64,
+    pub end: f64,
+    pub step: f64,
+    pub inclusive: bool,
+}
+
+impl Range {
+    pub fn new(
+        start: f64,
+        end: f64,
+        step: f64,
+        inclusive: bool,
+    ) -> Result<Range, LoxError> {
+        if step == 0.0 || !step.is_finite() {
+            error!("Range step must be a non-zero number.")
+        } else if !start.is_finite() || !end.is_finite() {
+            error!("Range bounds must be finite numbers.")
+        } else {
+            Ok(Range {
+                start,
+                end,
+                step,
+                inclusive,
+            })
+        }
+    }
+
+    pub fn len(&self) -> Result<usize, LoxError> {
+        let len = self.count();
+        if len < usize::MAX as f64 {
+            Ok(len as usize)
+        } else {
+            error!(format!(
+                "Range of {} numbers is too long to count.",
+                len
+            ))
+        }
+    }
+
+    // The number of elements as a float, so that it can't overflow. An
+    // end point within TOLERANCE steps of the sequence counts as on it,
+    // so `0..=0.3 step 0.1` ends with 0.3 despite rounding errors.
+    fn count(&self) -> f64 {
+        let steps = (self.end - self.start) / self.step;
+        let len = if self.inclusive {
+            (steps + TOLERANCE).floor() + 1.0
+        } else {
+            (steps - TOLERANCE).ceil()
+        };
+        len.max(0.0)
+    }
+
+    // Elements are computed from their index rather than by adding up
+    // steps so that fractional steps don't accumulate rounding errors.
+    // The last element of an inclusive range is never past its end.
+    pub fn get(&self, index: usize) -> Option<f64> {
+        if (index as f64) < self.count() {
+            let value = self.start + index as f64 * self.step;
+            if self.inclusive && (value - self.end) * self.step > 0.0 {
+                Some(self.end)
+            } else {
+                Some(value)
+            }
+        } else {
+            None
+        }
+    }
+
+    pub fn contains(&self, value: f64) -> bool {
+        let index = (value - self.start) / self.step;
+        let nearest = index.round();
+        (index - nearest).abs() < TOLERANCE
+            && nearest >= 0.0
+            && nearest < self.count()
+    }
+}
+
+// how far from a whole number of steps a value may be and still count
+// as one
+const TOLERANCE: f64 = 1e-9;
@@ -4,7 +4,7 @@ use lazy_static::lazy_static;
 use std::collections::HashMap;
 
 pub struct Scanner {
-    source: String,
+    source: Vec<char>, // indexed by character, not byte
     pub tokens: Vec<Token>,
     start: usize,
     current: usize,
@@ -27,6 +27,12 @@ lazy_static! {
         hash_map.insert("fun".to_owned(), TokenType::Fun);
         hash_map.insert("return".to_owned(), TokenType::Return);
         hash_map.insert("until".to_owned(), TokenType::Until);
+        hash_map.insert("yield".to_owned(), TokenType::Yield);
+        hash_map.insert("in".to_owned(), TokenType::In);
+        hash_map.insert("is".to_owned(), TokenType::Is);
+        hash_map.insert("assert".to_owned(), TokenType::Assert);
+        hash_map.insert("enum".to_owned(), TokenType::Enum);
+        hash_map.insert("struct".to_owned(), TokenType::Struct);
         hash_map
     };
 }
@@ -38,7 +44,7 @@ fn is_alphanumeric(c: char) -> bool {
 impl Scanner {
     pub fn new(source: &str) -> Scanner {
         Scanner {
-            source: source.to_owned(),
+            source: source.chars().collect(),
             tokens: Vec::new(),
             start: 0,
             current: 0,
@@ -60,19 +66,19 @@ impl Scanner {
             .expect("Source token is empty.");
         if token_type == TokenType::StrLit {
             // Trim surrounding quotes
-            let trim_str = &text[1..(text.len() - 1)];
+            let trim_str: String =
+                text[1..(text.len() - 1)].iter().collect();
             self.tokens
-                .push(Token::new(token_type, trim_str, self.line));
+                .push(Token::new(token_type, &trim_str, self.line));
         } else {
-            self.tokens.push(Token::new(token_type, text, self.line));
+            let text: String = text.iter().collect();
+            self.tokens.push(Token::new(token_type, &text, self.line));
         }
         Ok(())
     }
 
     fn matches(&mut self, expected: char) -> bool {
-        if self.is_end()
-            || self.source.chars().nth(self.current).unwrap() != expected
-        {
+        if self.is_end() || self.source[self.current] != expected {
             false
         } else {
             self.current += 1;
@@ -85,14 +91,53 @@ impl Scanner {
         match c {
             '(' => self.add_token(TokenType::LeftParen),
             ')' => self.add_token(TokenType::RightParen),
-            '.' => self.add_token(TokenType::Dot),
-            '-' => self.add_token(TokenType::Minus),
+            '.' => {
+                let new_type = if self.matches('.') {
+                    if self.matches('=') {
+                        TokenType::DotDotEqual
+                    } else {
+                        TokenType::DotDot
+                    }
+                } else {
+                    TokenType::Dot
+                };
+                self.add_token(new_type)
+            }
+            '-' => {
+                let new_type = if self.matches('>') {
+                    TokenType::Arrow
+                } else {
+                    TokenType::Minus
+                };
+                self.add_token(new_type)
+            }
             '+' => self.add_token(TokenType::Plus),
             '*' => self.add_token(TokenType::Star),
             ';' => self.add_token(TokenType::Semicolon),
             '{' => self.add_token(TokenType::LeftBrace),
             '}' => self.add_token(TokenType::RightBrace),
+            '[' => self.add_token(TokenType::LeftBracket),
+            ']' => self.add_token(TokenType::RightBracket),
             ',' => self.add_token(TokenType::Comma),
+            ':' => self.add_token(TokenType::Colon),
+            '|' => {
+                let new_type = if self.matches('>') {
+                    TokenType::Pipe
+                } else {
+                    TokenType::Unknown
+                };
+                self.add_token(new_type)
+            }
+            '?' => {
+                let new_type = if self.matches('?') {
+                    TokenType::QuestionQuestion
+                } else if self.matches('.') {
+                    TokenType::QuestionDot
+                } else {
+                    TokenType::Unknown
+                };
+                self.add_token(new_type)
+            }
             '/' => {
                 if self.matches('/') {
                     while self.peek() != '\n' && !self.is_end() {
@@ -142,7 +187,7 @@ impl Scanner {
             }
             '"' => self.scan_string(),
             _ => {
-                if c.is_digit(10) {
+                if c.is_ascii_digit() {
                     self.number()
                 } else if is_alphanumeric(c) {
                     self.identifier()
@@ -154,7 +199,7 @@ impl Scanner {
     }
 
     fn advance(&mut self) -> char {
-        let current_char = self.source.chars().nth(self.current).unwrap();
+        let current_char = self.source[self.current];
         self.current += 1;
         current_char
     }
@@ -163,26 +208,26 @@ impl Scanner {
         if self.is_end() {
             '\0'
         } else {
-            self.source.chars().nth(self.current).unwrap()
+            self.source[self.current]
         }
     }
 
     fn peek_next(&self) -> char {
-        if (self.current + 1) > self.source.len() {
+        if (self.current + 1) >= self.source.len() {
             '\0'
         } else {
-            self.source.chars().nth(self.current + 1).unwrap()
+            self.source[self.current + 1]
         }
     }
 
     fn number(&mut self) -> Result<(), LoxError> {
-        while self.peek().is_digit(10) {
+        while self.peek().is_ascii_digit() {
             self.advance();
         }
 
-        if self.peek() == '.' && self.peek_next().is_digit(10) {
+        if self.peek() == '.' && self.peek_next().is_ascii_digit() {
             self.advance();
-            while self.peek().is_digit(10) {
+            while self.peek().is_ascii_digit() {
                 self.advance();
             }
         }
@@ -205,9 +250,10 @@ impl Scanner {
         while is_alphanumeric(self.peek()) {
             self.advance();
         }
-        let sub_string = &self.source[self.start..self.current];
+        let sub_string: String =
+            self.source[self.start..self.current].iter().collect();
         let token_type = {
-            match KEYWORDS.get(sub_string) {
+            match KEYWORDS.get(&sub_string) {
                 None => TokenType::Identifier,
                 Some(t_type) => *t_type,
             }
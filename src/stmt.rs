@@ -1,5 +1,10 @@
 use crate::expr::Expr;
 use crate::token::Token;
+
+use std::rc::Rc;
+
+// Loops and the bodies of blocks and functions are shared rather than
+// copied, generators hold on to them while they are paused
 #[derive(Clone, Debug)]
 pub enum Stmt {
     Var(VarStmt),
@@ -7,9 +12,16 @@ pub enum Stmt {
     Expression(ExpressionStmt),
     Block(BlockStmt),
     If(IfStmt),
-    While(WhileStmt),
+    While(Rc<WhileStmt>),
     Function(FunStmt),
     Return(ReturnStmt),
+    Yield(YieldStmt),
+    ForIn(Rc<ForInStmt>),
+    For(Rc<ForStmt>),
+    Assert(AssertStmt),
+    Test(TestStmt),
+    Enum(EnumStmt),
+    Struct(StructStmt),
 }
 
 #[derive(Clone, Debug)]
@@ -25,15 +37,45 @@ pub struct WhileStmt {
     pub body: Box<Stmt>,
 }
 
+// for (init; condition; increment) body
 #[derive(Clone, Debug)]
-pub struct VarStmt {
+pub struct ForStmt {
+    pub init: Option<Box<Stmt>>,
+    pub condition: Option<Expr>,
+    pub increment: Option<Expr>,
+    pub body: Box<Stmt>,
+}
+
+impl ForStmt {
+    // the variables declared by the initializer, which every iteration
+    // gets its own copy of
+    pub fn loop_vars(&self) -> &[Token] {
+        match self.init.as_deref() {
+            Some(Stmt::Var(var)) => &var.names,
+            _ => &[],
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ForInStmt {
     pub name: Token,
+    pub iterable: Expr,
+    pub body: Box<Stmt>,
+}
+
+#[derive(Clone, Debug)]
+pub struct VarStmt {
+    pub names: Vec<Token>, // only more than one when unpacking
+    pub unpack: bool,      // var (q, r) = divmod(7, 2);
+    pub type_name: Option<Token>, // var n: number = 0;
     pub init: Option<Expr>,
 }
 
 #[derive(Clone, Debug)]
 pub struct PrintStmt {
     pub expr: Expr,
+    pub args: Vec<Expr>, // with any, `expr` is a format string
 }
 
 #[derive(Clone, Debug)]
@@ -43,14 +85,17 @@ pub struct ExpressionStmt {
 
 #[derive(Clone, Debug)]
 pub struct BlockStmt {
-    pub statements: Vec<Stmt>,
+    pub statements: Rc<[Stmt]>,
 }
 
 #[derive(Clone, Debug)]
 pub struct FunStmt {
     pub name: Token,
     pub params: Vec<Token>,
-    pub body: Vec<Stmt>,
+    pub param_types: Vec<Option<Token>>, // one per parameter
+    pub return_type: Option<Token>,
+    pub body: Rc<[Stmt]>,
+    pub is_generator: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -58,3 +103,46 @@ pub struct ReturnStmt {
     pub keyword: Token,
     pub value: Option<Expr>,
 }
+
+#[derive(Clone, Debug)]
+pub struct YieldStmt {
+    pub keyword: Token,
+    pub value: Option<Expr>,
+}
+
+// assert condition, "message";
+#[derive(Clone, Debug)]
+pub struct AssertStmt {
+    pub keyword: Token,
+    pub condition: Expr,
+    pub source: String, // the condition as written, for failure messages
+    pub message: Option<Expr>,
+}
+
+// test "name" { ... }, skipped unless run by `rena test`
+#[derive(Clone, Debug)]
+pub struct TestStmt {
+    pub keyword: Token,
+    pub name: String,
+    pub body: Rc<[Stmt]>,
+}
+
+// enum Shape { Circle(r), Rect(w, h), Empty }
+#[derive(Clone, Debug)]
+pub struct EnumStmt {
+    pub name: Token,
+    pub variants: Vec<EnumVariant>,
+}
+
+#[derive(Clone, Debug)]
+pub struct EnumVariant {
+    pub name: Token,
+    pub fields: Vec<Token>,
+}
+
+// struct Point { x, y }
+#[derive(Clone, Debug)]
+pub struct StructStmt {
+    pub name: Token,
+    pub fields: Vec<Token>,
+}
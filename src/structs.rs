@@ -0,0 +1,119 @@
+use crate::err::LoxError;
+use crate::interpreter::Value;
+use crate::stmt::StructStmt;
+
+use std::rc::Rc;
+
+// struct Point { x, y }
+//
+// Declaring a struct binds its name to a constructor that takes the
+// fields by position, by name or both: `Point(1, 2)`, `Point(y: 2, x: 1)`
+// and `Point(1, y: 2)` make the same record. Records are values, like
+// numbers: assigning one or passing it to a function hands over a copy.
+#[derive(Debug)]
+pub struct StructDef {
+    pub name: String,
+    fields: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Record {
+    pub def: Rc<StructDef>,
+    values: Vec<Value>, // one per field, in declaration order
+}
+
+impl StructDef {
+    pub fn new(stmt: &StructStmt) -> StructDef {
+        StructDef {
+            name: stmt.name.lexeme.clone(),
+            fields: stmt
+                .fields
+                .iter()
+                .map(|field| field.lexeme.clone())
+                .collect(),
+        }
+    }
+
+    pub fn construct(
+        def: &Rc<StructDef>,
+        args: Vec<Value>,
+        named: Vec<(String, Value)>,
+    ) -> Result<Value, LoxError> {
+        if args.len() > def.fields.len() {
+            return error!(format!(
+                "{}() expected {} arguments but got {}.",
+                def.name,
+                def.fields.len(),
+                args.len()
+            ));
+        }
+        let mut values: Vec<Option<Value>> =
+            args.into_iter().map(Some).collect();
+        values.resize(def.fields.len(), None);
+        for (name, value) in named {
+            let index = match def.fields.iter().position(|f| *f == name) {
+                Some(index) => index,
+                None => {
+                    return error!(format!(
+                        "{}() has no field '{}'.",
+                        def.name, name
+                    ))
+                }
+            };
+            if values[index].is_some() {
+                return error!(format!(
+                    "Field '{}' given twice to {}().",
+                    name, def.name
+                ));
+            }
+            values[index] = Some(value);
+        }
+        let values = values
+            .into_iter()
+            .zip(&def.fields)
+            .map(|(value, field)| match value {
+                Some(value) => Ok(value),
+                None => error!(format!(
+                    "Missing field '{}' for {}().",
+                    field, def.name
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Value::Record(Rc::new(Record {
+            def: def.clone(),
+            values,
+        })))
+    }
+}
+
+impl Record {
+    pub fn get(&self, name: &str) -> Result<Value, LoxError> {
+        match self.position(name) {
+            Some(index) => Ok(self.values[index].clone()),
+            None => self.missing(name),
+        }
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Result<&mut Value, LoxError> {
+        match self.position(name) {
+            Some(index) => Ok(&mut self.values[index]),
+            None => self.missing(name),
+        }
+    }
+
+    // `(name, value)` for every field, in declaration order
+    pub fn fields(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.def.fields.iter().zip(self.values.iter())
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.def.fields.iter().position(|field| field == name)
+    }
+
+    fn missing<T>(&self, name: &str) -> Result<T, LoxError> {
+        error!(format!(
+            "Struct '{}' has no field '{}'.",
+            self.def.name, name
+        ))
+    }
+}
@@ -0,0 +1,141 @@
+use crate::err::LoxError;
+use crate::interpreter::Interpreter;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::stmt::{BlockStmt, Stmt, TestStmt};
+
+use std::fs;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// `rena test [paths]` runs the `test` blocks of every script found under
+// `paths`. Each test gets a fresh interpreter that first runs the rest of
+// its script, so helpers defined there are available, and a test that
+// fails (or even panics) doesn't affect the others. Since the rest of the
+// script runs once per test, whatever it prints is repeated for each one.
+// The parser only accepts test blocks at the top level of a file, so none
+// can be missed. Returns whether every test passed.
+pub fn run_tests(
+    paths: &[String],
+    new_interpreter: &dyn Fn() -> Interpreter,
+) -> bool {
+    let started = Instant::now();
+    let (mut passed, mut failed) = (0, 0);
+    let mut files = vec![];
+    for path in paths {
+        if let Err(err) = discover(Path::new(path), &mut files) {
+            println!("FAIL {}: {}", path, err);
+            failed += 1;
+        }
+    }
+
+    for file in files {
+        let statements = match parse_file(&file) {
+            Ok(statements) => statements,
+            Err(err) => {
+                println!("FAIL {}: {}", file.display(), err);
+                failed += 1;
+                continue;
+            }
+        };
+        let (tests, setup): (Vec<_>, Vec<_>) = statements
+            .into_iter()
+            .partition(|stmt| matches!(stmt, Stmt::Test(_)));
+        if tests.is_empty() {
+            continue;
+        }
+        println!("{}", file.display());
+        for test in tests {
+            if let Stmt::Test(test) = test {
+                let start = Instant::now();
+                let result = run_test(new_interpreter(), &setup, &test);
+                let time = millis(start.elapsed());
+                match result {
+                    Ok(()) => {
+                        passed += 1;
+                        println!("  PASS {} ({})", test.name, time);
+                    }
+                    Err(message) => {
+                        failed += 1;
+                        println!("  FAIL {} ({})", test.name, time);
+                        println!("       {}", message);
+                    }
+                }
+            }
+        }
+    }
+
+    if passed + failed == 0 {
+        println!("No tests found.");
+    } else {
+        println!(
+            "\n{} passed, {} failed in {}",
+            passed,
+            failed,
+            millis(started.elapsed())
+        );
+    }
+    failed == 0
+}
+
+// Collects `path` if it is a file, or the .lox files under it if it is
+// a directory, in a stable order
+fn discover(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if path.is_dir() {
+        let mut entries = fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        entries.sort();
+        for entry in entries {
+            if entry.is_dir()
+                || entry.extension().is_some_and(|ext| ext == "lox")
+            {
+                discover(&entry, files)?;
+            }
+        }
+        Ok(())
+    } else if path.exists() {
+        files.push(path.to_path_buf());
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::NotFound, "No such file."))
+    }
+}
+
+fn parse_file(path: &Path) -> Result<Vec<Stmt>, LoxError> {
+    match fs::read_to_string(path) {
+        Ok(src) => {
+            let tokens = Scanner::new(&src).scan_tokens()?;
+            Parser::new(tokens).parse()
+        }
+        Err(err) => error!(format!("Source file cannot be read: {}", err)),
+    }
+}
+
+fn run_test(
+    mut intp: Interpreter,
+    setup: &[Stmt],
+    test: &TestStmt,
+) -> Result<(), String> {
+    let body = Stmt::Block(BlockStmt {
+        statements: test.body.clone(),
+    });
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+        intp.interpret(setup)?;
+        intp.interpret(&[body])
+    }));
+    match outcome {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(LoxError::Exit(code))) => {
+            Err(format!("Exited with code {}.", code))
+        }
+        Ok(Err(err)) => Err(err.to_string()),
+        Err(_) => Err("The interpreter panicked.".to_owned()),
+    }
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.1} ms", duration.as_secs_f64() * 1000.0)
+}
@@ -3,7 +3,10 @@ pub enum TokenType {
     LeftParen,
     RightParen,
     Dot,
+    DotDot,
+    DotDotEqual,
     Minus,
+    Arrow,
     Plus,
     Slash,
     Star,
@@ -11,7 +14,13 @@ pub enum TokenType {
     Eof,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
+    Colon,
+    Pipe,             // |>
+    QuestionQuestion, // ??
+    QuestionDot,      // ?.
 
     Bang,
     BangEqual,
@@ -38,6 +47,12 @@ pub enum TokenType {
     Fun,
     Return,
     Until,
+    Yield,
+    In,
+    Is,
+    Assert,
+    Enum,
+    Struct,
 }
 
 #[derive(Clone, Debug)]